@@ -0,0 +1,223 @@
+use crate::{
+    block::{Block, Header},
+    primitive::*,
+    transaction::{Transaction, Transactions},
+};
+use anyhow::{anyhow, Result};
+use blake2::Digest;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Short transaction id used in compact block announcements
+pub type ShortId = [u8; 6];
+
+/// Block announcement carrying the header and short ids of the block transactions
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CompactBlock {
+    pub header: Header,
+    pub short_ids: Vec<ShortId>,
+}
+
+impl CompactBlock {
+    /// Creating a compact block from a full block
+    pub fn from_block(block: &Block) -> Result<Self> {
+        let block_hash = block.header.hash()?;
+
+        let short_ids = block
+            .transactions
+            .to_vec_hash()?
+            .iter()
+            .map(|hash| Self::short_id(&block_hash, hash))
+            .collect();
+
+        Ok(Self {
+            header: block.header.clone(),
+            short_ids,
+        })
+    }
+
+    /// Short id of a transaction salted with the block hash
+    pub fn short_id(block_hash: &Hash, transaction_hash: &Hash) -> ShortId {
+        let mut hasher = Blake2b256::new();
+
+        hasher.update(block_hash);
+        hasher.update(transaction_hash);
+        let hash = hasher.finalize();
+
+        let mut short_id = [0u8; 6];
+        short_id.copy_from_slice(&hash[0..6]);
+        short_id
+    }
+
+    /// Reconstructing the block from the transactions of the mempool
+    pub fn reconstruct(&self, mempool: &[Transaction]) -> Result<PartialBlock> {
+        let block_hash = self.header.hash()?;
+
+        // Short ids that match more than one transaction are treated as missing
+        let mut candidates: HashMap<ShortId, Option<&Transaction>> = HashMap::new();
+        for transaction in mempool {
+            let short_id = Self::short_id(&block_hash, &transaction.hash()?);
+
+            candidates
+                .entry(short_id)
+                .and_modify(|candidate| *candidate = None)
+                .or_insert(Some(transaction));
+        }
+
+        let transactions = self
+            .short_ids
+            .iter()
+            .map(|short_id| candidates.get(short_id).copied().flatten().cloned())
+            .collect();
+
+        Ok(PartialBlock {
+            header: self.header.clone(),
+            transactions,
+        })
+    }
+}
+
+/// Block being reconstructed from a compact block announcement
+#[derive(Debug)]
+pub struct PartialBlock {
+    pub header: Header,
+    transactions: Vec<Option<Transaction>>,
+}
+
+impl PartialBlock {
+    /// Getting the indexes of transactions that are not found in the mempool
+    pub fn missing(&self) -> Vec<u64> {
+        self.transactions
+            .iter()
+            .enumerate()
+            .filter(|(_, transaction)| transaction.is_none())
+            .map(|(index, _)| index as u64)
+            .collect()
+    }
+
+    /// Filling the missing transactions in the order of their indexes
+    pub fn fill(&mut self, transactions: Vec<Transaction>) -> Result<()> {
+        let missing = self.missing();
+
+        if missing.len() != transactions.len() {
+            return Err(anyhow!(
+                "Number of missing transactions does not match: expected {}, received {}",
+                missing.len(),
+                transactions.len()
+            ));
+        }
+
+        for (index, transaction) in missing.into_iter().zip(transactions) {
+            self.transactions[index as usize] = Some(transaction);
+        }
+
+        Ok(())
+    }
+
+    /// Converting to a full block if all transactions are present
+    pub fn into_block(self) -> Result<Block> {
+        let mut transactions = Transactions::default();
+
+        for transaction in self.transactions {
+            transactions.push(transaction.ok_or_else(|| anyhow!("Block is not complete"))?);
+        }
+
+        Ok(Block {
+            header: self.header,
+            transactions,
+        })
+    }
+}
+
+/// Request for the data of an announced compact block
+#[derive(Serialize, Deserialize, Debug)]
+pub enum BlockTransactionsRequest {
+    Transactions { block_hash: Hash, indexes: Vec<u64> },
+    Block { block_hash: Hash },
+}
+
+/// Response with the data of an announced compact block
+#[derive(Serialize, Deserialize, Debug)]
+pub enum BlockTransactionsResponse {
+    Transactions {
+        block_hash: Hash,
+        transactions: Vec<Transaction>,
+    },
+    Block(Box<Block>),
+    NotFound {
+        block_hash: Hash,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{account::Account, constants::*, transaction::Data, wallet};
+
+    fn transfer(amount: u64) -> Transaction {
+        let (secret_key, public_key) = wallet::generate();
+        let account = Account::from_public_key(public_key, Network::Testnet);
+
+        let data = Data::Transfer {
+            recipient: EMPTY_ADDRESS,
+            amount,
+            attachment: String::new(),
+        };
+
+        let mut transaction = Transaction::new(
+            0,
+            account.address,
+            public_key,
+            1,
+            100000,
+            0,
+            TRANSACTION_LIFETIME_BLOCKS,
+            data,
+        );
+        transaction.sign(&secret_key).unwrap();
+        transaction
+    }
+
+    #[test]
+    fn reconstruct_from_mempool() {
+        let mut transactions = Transactions::default();
+        for amount in 0..3 {
+            transactions.push(transfer(amount));
+        }
+
+        let header = Header::new(
+            0,
+            1,
+            0,
+            EMPTY_HASH,
+            EMPTY_ADDRESS,
+            EMPTY_PUBLIC_KEY,
+            0,
+            EMPTY_HASH,
+            3,
+        );
+        let block = Block {
+            header,
+            transactions,
+        };
+        let compact_block = CompactBlock::from_block(&block).unwrap();
+
+        let mempool = vec![
+            block.transactions.0[0].clone(),
+            block.transactions.0[2].clone(),
+        ];
+        let mut partial_block = compact_block.reconstruct(&mempool).unwrap();
+        assert_eq!(partial_block.missing(), vec![1]);
+
+        assert!(partial_block.fill(vec![]).is_err());
+        partial_block
+            .fill(vec![block.transactions.0[1].clone()])
+            .unwrap();
+
+        let reconstructed = partial_block.into_block().unwrap();
+        assert_eq!(
+            reconstructed.transactions.to_vec_hash().unwrap(),
+            block.transactions.to_vec_hash().unwrap()
+        );
+    }
+}
@@ -1,13 +1,18 @@
 use crate::{
     block::{Block, Header},
     constants::*,
+    primitive::*,
     transaction::Transactions,
 };
+use anyhow::Result;
+use blake2::Digest;
 
 pub fn simple() -> Block {
     let timestamp = 0;
 
+    // The genesis block is shared by the networks, its chain id is derived from it
     let header = Header::new(
+        0,
         0,
         timestamp,
         EMPTY_HASH,
@@ -24,3 +29,15 @@ pub fn simple() -> Block {
         transactions,
     }
 }
+
+/// Chain id of the network signed by the headers and the transactions, derived from the
+/// network byte and the genesis block hash
+pub fn chain_id(network: Network) -> Result<ChainId> {
+    let mut hasher = Blake2b256::new();
+    hasher.update(CHAIN_ID_TAG);
+    hasher.update([network as u8]);
+    hasher.update(simple().header.hash()?);
+    let hash = hasher.finalize();
+
+    Ok(ChainId::from_le_bytes([hash[0], hash[1], hash[2], hash[3]]))
+}
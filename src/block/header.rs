@@ -5,6 +5,7 @@ use serde_big_array::BigArray;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Header {
+    pub chain_id: ChainId,
     pub height: u64,
     pub timestamp: u128,
     pub prev_block: Hash,
@@ -12,6 +13,7 @@ pub struct Header {
     pub generator_public_key: PublicKey,
     pub reward: u64,
     pub root: Hash,
+    pub state_root: Hash,
     pub transactions_count: u64,
     pub pow_hash: Hash,
     pub n_bits: u32,
@@ -21,7 +23,9 @@ pub struct Header {
 }
 
 impl Header {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        chain_id: ChainId,
         height: u64,
         timestamp: u128,
         prev_block: Hash,
@@ -32,6 +36,7 @@ impl Header {
         transactions_count: u64,
     ) -> Self {
         Self {
+            chain_id,
             height,
             timestamp,
             prev_block,
@@ -39,6 +44,7 @@ impl Header {
             generator_public_key,
             reward,
             root,
+            state_root: EMPTY_HASH,
             transactions_count,
             pow_hash: EMPTY_HASH,
             n_bits: 0,
@@ -47,7 +53,7 @@ impl Header {
         }
     }
 
-    /// Block header PoW hash calculation
+    /// Block header PoW hash calculation, the hash commits to the mining target `n_bits`
     pub fn pow_hash(&mut self, randomx_vm: &RandomXVMInstance) -> Result<()> {
         let bytes = randomx_vm.calculate_hash(&self.hash()?)?;
 
@@ -75,13 +81,16 @@ impl Cryptography for Header {
     fn as_data_for_signing(&self) -> Result<Vec<u8>> {
         let mut bytes: Vec<u8> = vec![];
 
+        bytes.extend_from_slice(HEADER_SIGNING_TAG);
+        bytes.extend_from_slice(&self.chain_id.to_le_bytes());
         bytes.extend_from_slice(&self.height.to_le_bytes());
         bytes.extend_from_slice(&self.timestamp.to_le_bytes());
         bytes.extend_from_slice(&self.prev_block);
-        bytes.extend_from_slice(&self.generator);
+        bytes.extend_from_slice(self.generator.as_ref());
         bytes.extend_from_slice(&self.generator_public_key);
         bytes.extend_from_slice(&self.reward.to_le_bytes());
         bytes.extend_from_slice(&self.root);
+        bytes.extend_from_slice(&self.state_root);
         bytes.extend_from_slice(&self.transactions_count.to_le_bytes());
         bytes.extend_from_slice(&self.n_bits.to_le_bytes());
         bytes.extend_from_slice(&self.nonce.to_le_bytes());
@@ -94,7 +103,9 @@ impl Validation for Header {
     fn is_valid(&self, state: &State) -> Result<()> {
         self.signature_verify()?;
 
-        if state.last_header.height + 1 != self.height {
+        if state.chain_id() != self.chain_id {
+            Err(anyhow!("Header of another chain: {self:?}"))
+        } else if state.last_header.height + 1 != self.height {
             Err(anyhow!("New header is not the next: {self:?}"))
         } else if state.last_header.timestamp > self.timestamp {
             Err(anyhow!(
@@ -125,6 +136,7 @@ mod tests {
         let account = Account::from_public_key(public_key, Network::Testnet);
 
         let mut header = Header::new(
+            0,
             0,
             0,
             EMPTY_HASH,
@@ -1,3 +1,4 @@
+pub mod compact;
 pub mod genesis;
 mod header;
 
@@ -17,6 +18,20 @@ pub struct Block {
     pub transactions: Transactions,
 }
 
+impl Block {
+    /// Checking the chain id and the state root of the block against the state of the
+    /// previous block
+    pub fn check_state(&self, state: &State) -> Result<()> {
+        if state.chain_id() != self.header.chain_id {
+            Err(anyhow!("Header of another chain: {:?}", self.header))
+        } else if state.calculate_state_root(self)? != self.header.state_root {
+            Err(anyhow!("State root does not match: {:?}", self.header))
+        } else {
+            Ok(())
+        }
+    }
+}
+
 impl Validation for Block {
     fn is_valid(&self, state: &State) -> Result<()> {
         if let Err(error) = self.header.is_valid(state) {
@@ -31,8 +46,11 @@ impl Validation for Block {
                 "Merkle tree hash does not match: {:?}",
                 self.header
             ))
+        } else if let Err(error) = self.check_state(state) {
+            Err(error)
         } else {
             for transaction in &self.transactions.0 {
+                transaction.check_expiry(self.header.height, self.header.timestamp)?;
                 transaction.is_valid(state)?;
             }
 
@@ -3,14 +3,29 @@ use crate::primitive::*;
 pub const CARGO_PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub const ADDRESS_PREFIX: u8 = 0xed;
+pub const ADDRESS_LENGTH: usize = 32;
+/// Number of checksum bytes appended to the text form of an address
+pub const ADDRESS_CHECKSUM_LENGTH: usize = 4;
 
 /// Empty primitives
-pub const EMPTY_ADDRESS: Address = [0u8; 32];
+pub const EMPTY_ADDRESS: Address = Address([0u8; ADDRESS_LENGTH]);
 pub const EMPTY_HASH: Hash = [0u8; 32];
 pub const EMPTY_PUBLIC_KEY: PublicKey = [0u8; 32];
 pub const EMPTY_SECRET_KEY: SecretKey = [0u8; 32];
 pub const EMPTY_SIGNATURE: Signature = [0u8; 64];
 
+/// Domain separation tags of the chain id and the signed data
+pub const CHAIN_ID_TAG: &[u8] = b"gem-chain-id";
+pub const HEADER_SIGNING_TAG: &[u8] = b"gem-header";
+pub const TRANSACTION_SIGNING_TAG: &[u8] = b"gem-transaction";
+
+/// Transactions are included in blocks up to this time after their timestamp, milliseconds
+pub const TRANSACTION_LIFETIME: u128 = 2 * 60 * 60 * 1000;
+/// Maximum time the transaction timestamp is ahead of the block, milliseconds
+pub const MAX_TRANSACTION_FUTURE: u128 = 10 * 60 * 1000;
+/// Number of blocks after the last block the wallet sets as the transaction expiry height
+pub const TRANSACTION_LIFETIME_BLOCKS: u64 = 480;
+
 /// RandomX change key
 pub const RANDOMX_CHANGE_KEY: u64 = 8640;
 
@@ -19,10 +34,57 @@ pub const LWMA_NUMBER_BLOCKS: u64 = 50;
 
 /// Swarm topics
 pub const BLOCK_TOPIC: &str = "block";
-pub const TRANSACTION_TOPIC: &str = "transaction";
 
 // Swarm request response
 pub const MAX_TRANSMIT_SIZE: usize = 1_000_000;
+/// Maximum size of a request, the responses are limited by `MAX_TRANSMIT_SIZE`
+pub const MAX_REQUEST_SIZE: usize = 65_536;
+
+/// Synchronization protocol version prefixed to each message. Peers of an older version follow
+/// other consensus rules: version 2 adds the state root to the headers, version 3 signs the
+/// chain id, commits the PoW hash to the mining target and accepts only the next sequence
+/// number of the sender, version 4 signs the expiry height of the transactions
+pub const SYNC_PROTOCOL_VERSION: u8 = 4;
+/// Maximum number of blocks in a synchronization response
+pub const MAX_SYNC_BLOCKS: u64 = 500;
+/// Maximum number of headers in a synchronization response
+pub const MAX_SYNC_HEADERS: u64 = 2_000;
+
+/// Maximum number of compact blocks awaiting missing transactions
+pub const MAX_PARTIAL_BLOCKS: usize = 16;
+
+/// Maximum number of transaction hashes in an inventory message
+pub const MAX_INVENTORY_SIZE: usize = 1_000;
+/// Maximum number of transaction hashes remembered per peer
+pub const MAX_KNOWN_INVENTORY: usize = 50_000;
+
+/// Rate limits of incoming requests per peer (requests per second and burst)
+pub const REQUEST_RATE: f64 = 1.0;
+pub const REQUEST_BURST: f64 = 10.0;
+/// Rate limits of incoming gossip messages per peer (messages per second and burst)
+pub const GOSSIP_RATE: f64 = 50.0;
+pub const GOSSIP_BURST: f64 = 200.0;
+/// Maximum number of connections from a single IP address
+pub const MAX_CONNECTIONS_PER_IP: usize = 4;
+/// Maximum number of outgoing requests awaiting a response per peer
+pub const MAX_OUTSTANDING_REQUESTS: usize = 16;
+
+/// Number of blocks between progress reports of long operations
+pub const PROGRESS_INTERVAL: u64 = 1_000;
+
+/// Number of blocks rescanned for the watched addresses while the state is locked
+pub const RESCAN_CHUNK_SIZE: u64 = 100;
+/// Number of keys deleted by one write when a column family is cleared
+pub const CLEAR_BATCH_SIZE: usize = 10_000;
+
+/// Minimum number of recent blocks whose transactions are kept in the pruning mode
+pub const MIN_PRUNE_DEPTH: u64 = 100;
+
+/// Minimum transaction fee
+pub const MIN_FEE: u64 = 100_000;
+
+/// Coin type of the HD wallet derivation path
+pub const HD_COIN_TYPE: u32 = 7337;
 
 /// RocksDB column family
 pub const BLOCK_HEADERS: &str = "block_headers";
@@ -32,4 +94,7 @@ pub const TRANSACTIONS: &str = "transactions";
 pub const ACCOUNTS: &str = "accounts";
 pub const ACCOUNTS_PUBLIC_KEY: &str = "account_public_key";
 pub const ACCOUNTS_TRANSACTIONS: &str = "accounts_transactions";
+pub const STATE_TREE: &str = "state_tree";
+pub const WATCHED: &str = "watched";
+pub const WATCHED_HISTORY: &str = "watched_history";
 pub const INFO: &str = "info";
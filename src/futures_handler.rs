@@ -1,10 +1,22 @@
 use crate::{
-    block::Block,
+    block::{
+        compact::{
+            BlockTransactionsRequest, BlockTransactionsResponse, CompactBlock, PartialBlock,
+        },
+        Block,
+    },
     constants::*,
     primitive::*,
     state::State,
-    swarm::behaviour::{Behaviour, SyncRequest, SyncResponse},
-    transaction::Transaction,
+    swarm::{
+        behaviour::{
+            Behaviour, CompactBlockRequest, CompactBlockResponse, SyncRequest, SyncResponse,
+            TransactionRequest, TransactionResponse,
+        },
+        inventory::KnownInventory,
+        limits::Limits,
+    },
+    transaction::{InventoryRequest, InventoryResponse, MerkleTree, Transaction},
 };
 use anyhow::{anyhow, Result};
 use async_std::sync::{Arc, RwLock};
@@ -12,13 +24,27 @@ use base58::ToBase58;
 use libp2p::{gossipsub, request_response::ResponseChannel, swarm::Swarm, PeerId};
 use rand::prelude::*;
 
-pub async fn sync_blocks(state: Arc<RwLock<State>>, swarm: &mut Swarm<Behaviour>) -> Result<()> {
+pub async fn sync_blocks(
+    state: Arc<RwLock<State>>,
+    swarm: &mut Swarm<Behaviour>,
+    limits: &mut Limits,
+) -> Result<()> {
     let state = state.read().await;
 
     if let Some(peer_id) = swarm.connected_peers().choose(&mut thread_rng()).cloned() {
-        let data = bincode::serialize(&state.last_header.height)
-            .map_err(|error| anyhow!("Failed to serialize height for sync: {error:?}"))?;
-        let sync_request = SyncRequest(data);
+        if !limits.start_request(&peer_id) {
+            log::warn!(
+                "Too many outstanding requests to peer: {}",
+                peer_id.to_base58()
+            );
+            return Ok(());
+        }
+
+        let height = state.last_header.height;
+        let sync_request = SyncRequest::GetBlockRange {
+            start: height + 1,
+            end: height + MAX_SYNC_BLOCKS,
+        };
 
         swarm
             .behaviour_mut()
@@ -56,8 +82,9 @@ pub async fn mining_handler(
             log::warn!("Put block failed: {error:?}");
         }
 
-        let block_bytes = bincode::serialize(&block)
-            .map_err(|error| anyhow!("Failed to serialize block: {error:?}"))?;
+        let compact_block = CompactBlock::from_block(&block)?;
+        let block_bytes = bincode::serialize(&compact_block)
+            .map_err(|error| anyhow!("Failed to serialize compact block: {error:?}"))?;
 
         if let Err(error) = swarm
             .behaviour_mut()
@@ -77,98 +104,235 @@ pub async fn mining_handler(
 pub async fn sync_request(
     state: Arc<RwLock<State>>,
     swarm: &mut Swarm<Behaviour>,
+    limits: &mut Limits,
+    peer: PeerId,
     request: SyncRequest,
     channel: ResponseChannel<SyncResponse>,
 ) -> Result<()> {
-    let state = state.read().await;
+    // The request is dropped without a response
+    if !limits.allow_request(&peer) {
+        log::warn!("Sync request is throttled: {}", peer.to_base58());
+        return Ok(());
+    }
 
-    let mut height: u64 = bincode::deserialize(&request.0)
-        .map_err(|error| anyhow!("Failed to deserialize height for sync: {error:?}"))?;
-    log::trace!(
-        "Received synchronization request. Received height: {}",
-        height
-    );
+    let state = state.read().await;
 
-    if state.last_header.height > height {
-        let mut size = 0;
-        let mut blocks = vec![];
+    log::trace!("Received synchronization request: {:?}", request);
 
-        loop {
-            height += 1;
+    // Transactions of blocks up to the base height are pruned, the genesis block is always kept
+    let base_height = state.database.get_base_height()?;
+    let is_pruned = |height: u64| height > 0 && height <= base_height;
 
-            if let Ok(block) = state.database.get_block_from_height(height) {
-                size += bincode::serialize(&block)?.len();
-                if size > MAX_TRANSMIT_SIZE {
-                    break;
+    let response = match request {
+        SyncRequest::GetHeaders { locator, limit } => {
+            if limit > MAX_SYNC_HEADERS || locator.len() as u64 > MAX_SYNC_HEADERS {
+                SyncResponse::Refused(format!("Headers limit exceeds {MAX_SYNC_HEADERS}"))
+            } else {
+                // The first locator hash on the main chain is the fork point
+                let start = locator
+                    .iter()
+                    .filter_map(|hash| state.database.get_block_header_from_hash(*hash).ok())
+                    .find(|header| {
+                        state
+                            .database
+                            .get_block_header_from_height(header.height)
+                            .map(|main| main.hash().ok() == header.hash().ok())
+                            .unwrap_or(false)
+                    })
+                    .map(|header| header.height + 1)
+                    .unwrap_or(1);
+
+                let mut headers = vec![];
+                for height in start..start.saturating_add(limit) {
+                    match state.database.get_block_header_from_height(height) {
+                        Ok(header) => headers.push(header),
+                        Err(_) => break,
+                    }
                 }
 
-                blocks.push(block);
+                SyncResponse::Headers(headers)
+            }
+        }
+        SyncRequest::GetBlocks(hashes) => {
+            if hashes.len() as u64 > MAX_SYNC_BLOCKS {
+                SyncResponse::Refused(format!("Blocks limit exceeds {MAX_SYNC_BLOCKS}"))
+            } else if hashes.iter().any(|hash| {
+                state
+                    .database
+                    .get_block_header_from_hash(*hash)
+                    .map(|header| is_pruned(header.height))
+                    .unwrap_or(false)
+            }) {
+                SyncResponse::Pruned { base_height }
             } else {
-                break;
+                let blocks = hashes
+                    .iter()
+                    .map_while(|hash| state.database.get_block_from_hash(*hash).ok());
+
+                collect_blocks(blocks)?
             }
         }
+        SyncRequest::GetBlockRange { start, end } => {
+            if start > end {
+                SyncResponse::Refused(format!("Invalid block range: {start}..{end}"))
+            } else if end - start >= MAX_SYNC_BLOCKS {
+                SyncResponse::Refused(format!("Block range exceeds {MAX_SYNC_BLOCKS}"))
+            } else if is_pruned(start) {
+                SyncResponse::Pruned { base_height }
+            } else {
+                let blocks = (start..=end)
+                    .map_while(|height| state.database.get_block_from_height(height).ok());
 
-        let data = bincode::serialize(&blocks)
-            .map_err(|error| anyhow!("Failed to serialize blocks: {error:?}"))?;
+                collect_blocks(blocks)?
+            }
+        }
+        SyncRequest::GetTransaction(hash) => SyncResponse::Transaction(
+            state
+                .get_transaction_mempool(&hash)
+                .cloned()
+                .or_else(|| state.database.get_transaction(hash).ok())
+                .map(Box::new),
+        ),
+    };
+
+    // The response is collected, the state is no longer needed for serialization
+    drop(state);
+
+    if let Err(error) = swarm
+        .behaviour_mut()
+        .request_response
+        .send_response(channel, response)
+    {
+        log::warn!("Send response failed: {error:?}");
+    }
 
-        log::trace!("Prepared blocks for response: {}", height - 1);
+    Ok(())
+}
 
-        if let Err(error) = swarm
-            .behaviour_mut()
-            .request_response
-            .send_response(channel, SyncResponse(data))
-        {
-            log::warn!("Send response failed: {error:?}");
+/// Collecting blocks while the encoded response fits in `MAX_TRANSMIT_SIZE`, an empty response
+/// means that the responder has no more blocks. The request is refused if the first block does
+/// not fit, the requester could not read the response
+fn collect_blocks(blocks: impl Iterator<Item = Block>) -> Result<SyncResponse> {
+    // The version byte, the enum tag and the length prefix of the blocks
+    let mut size = 1 + bincode::serialized_size(&SyncResponse::Blocks(vec![]))? as usize;
+    let mut collected = vec![];
+
+    for block in blocks {
+        size += bincode::serialized_size(&block)? as usize;
+        if size > MAX_TRANSMIT_SIZE {
+            if collected.is_empty() {
+                return Ok(SyncResponse::Refused(format!(
+                    "Block {} exceeds {MAX_TRANSMIT_SIZE} bytes",
+                    block.header.height
+                )));
+            }
+            break;
         }
-    } else {
-        log::trace!("The resulting height is the last available");
 
-        if let Err(error) = swarm
-            .behaviour_mut()
-            .request_response
-            .send_response(channel, SyncResponse(vec![]))
-        {
-            log::warn!("Send response failed: {error:?}");
-        }
+        collected.push(block);
     }
 
-    Ok(())
+    Ok(SyncResponse::Blocks(collected))
 }
 
 /// Incoming response handler
 pub async fn sync_response(
     state: Arc<RwLock<State>>,
     swarm: &mut Swarm<Behaviour>,
+    limits: &mut Limits,
     peer: PeerId,
     response: SyncResponse,
 ) -> Result<()> {
     let mut state = state.write().await;
 
-    if let Ok(blocks) = bincode::deserialize::<Vec<Block>>(response.0.as_slice()) {
-        log::trace!(
-            "A synchronization response is received. Number of blocks received: {}",
-            blocks.len()
-        );
+    match response {
+        SyncResponse::Blocks(blocks) if blocks.is_empty() => {
+            log::trace!("The last available height is reached");
+            state.is_sync = true;
+        }
+        SyncResponse::Blocks(blocks) => {
+            log::trace!(
+                "A synchronization response is received. Number of blocks received: {}",
+                blocks.len()
+            );
 
-        for block in blocks {
-            log::info!("New block received: {}", block.header.height);
+            for block in blocks {
+                log::info!("New block received: {}", block.header.height);
 
-            if let Ok(()) = block.is_valid(&state) {
-                if let Err(error) = state.put_block(&block) {
-                    log::warn!("Put block failed: {error:?}");
+                if let Ok(()) = block.is_valid(&state) {
+                    if let Err(error) = state.put_block(&block) {
+                        log::warn!("Put block failed: {error:?}");
+                    }
+                } else {
+                    swarm.ban_peer_id(peer);
+                    log::warn!(
+                        "Peer is banned for providing invalid block: {}",
+                        peer.to_base58()
+                    )
                 }
-            } else {
-                swarm.ban_peer_id(peer);
-                log::warn!(
-                    "Peer is banned for providing invalid block: {}",
-                    peer.to_base58()
-                )
+            }
+
+            state.is_sync = false;
+        }
+        SyncResponse::Headers(headers) => {
+            let hashes = headers
+                .iter()
+                .map(|header| header.hash())
+                .collect::<Result<Vec<Hash>>>()?
+                .into_iter()
+                .filter(|hash| state.database.get_block_header_from_hash(*hash).is_err())
+                .take(MAX_SYNC_BLOCKS as usize)
+                .collect::<Vec<Hash>>();
+
+            if !hashes.is_empty() {
+                if !limits.start_request(&peer) {
+                    log::warn!(
+                        "Too many outstanding requests to peer: {}",
+                        peer.to_base58()
+                    );
+                    return Ok(());
+                }
+
+                swarm
+                    .behaviour_mut()
+                    .request_response
+                    .send_request(&peer, SyncRequest::GetBlocks(hashes));
             }
         }
+        SyncResponse::Transaction(Some(transaction)) => {
+            let hash = transaction.hash()?;
 
-        state.is_sync = false;
-    } else {
-        state.is_sync = true;
+            if !state.contains_transaction(&hash) {
+                if let Ok(()) = transaction.is_valid(&state) {
+                    if let Err(error) = state.put_transaction_mempool(*transaction) {
+                        log::warn!("Put transaction failed: {error:?}");
+                    }
+                } else {
+                    swarm.ban_peer_id(peer);
+                    log::warn!(
+                        "Peer is banned for providing invalid transaction: {}",
+                        peer.to_base58()
+                    );
+                }
+            }
+        }
+        SyncResponse::Transaction(None) => {
+            log::trace!("Requested transaction is not found: {}", peer.to_base58());
+        }
+        SyncResponse::Pruned { base_height } => {
+            log::info!(
+                "Peer has pruned blocks up to height {}: {}",
+                base_height,
+                peer.to_base58()
+            );
+        }
+        SyncResponse::Refused(reason) => {
+            log::warn!(
+                "Synchronization request is refused by {}: {}",
+                peer.to_base58(),
+                reason
+            );
+        }
     }
 
     Ok(())
@@ -178,58 +342,570 @@ pub async fn sync_response(
 pub async fn gossipsub_handler(
     state: Arc<RwLock<State>>,
     swarm: &mut Swarm<Behaviour>,
+    limits: &mut Limits,
+    propagation_source: PeerId,
     message: gossipsub::Message,
 ) -> Result<()> {
+    if !limits.allow_gossip(&propagation_source) {
+        log::trace!(
+            "Gossip message is throttled: {}",
+            propagation_source.to_base58()
+        );
+        return Ok(());
+    }
+
     let mut state = state.write().await;
 
-    if state.is_sync {
-        match message.topic.as_str() {
-            BLOCK_TOPIC => {
-                let block = bincode::deserialize::<Block>(&message.data)
-                    .map_err(|error| anyhow!("Failed to deserialize block: {error:?}"))?;
+    if state.is_sync && message.topic.as_str() == BLOCK_TOPIC {
+        let compact_block = bincode::deserialize::<CompactBlock>(&message.data)
+            .map_err(|error| anyhow!("Failed to deserialize compact block: {error:?}"))?;
+        let block_hash = compact_block.header.hash()?;
+
+        log::info!("New block received: {}", compact_block.header.height);
+
+        let partial_block = compact_block.reconstruct(state.mempool())?;
+        let missing = partial_block.missing();
+
+        if !missing.is_empty() {
+            log::trace!(
+                "Requesting {} missing transactions of the block: {}",
+                missing.len(),
+                block_hash.to_base58()
+            );
+
+            state.put_partial_block(block_hash, partial_block);
+            send_compact_block_request(
+                &mut state,
+                swarm,
+                limits,
+                &propagation_source,
+                BlockTransactionsRequest::Transactions {
+                    block_hash,
+                    indexes: missing,
+                },
+            )?;
+
+            return Ok(());
+        }
 
-                log::info!("New block received: {}", block.header.height);
+        let block = match complete_partial_block(
+            &mut state,
+            swarm,
+            limits,
+            &propagation_source,
+            block_hash,
+            partial_block,
+        )? {
+            Some(block) => block,
+            None => return Ok(()),
+        };
+
+        if let Ok(()) = block.is_valid(&state) {
+            if let Err(error) = state.put_block(&block) {
+                log::warn!("Put block failed: {error:?}");
+            }
+        } else if let Some(peer_id) = message.source {
+            swarm.ban_peer_id(peer_id);
+            log::warn!(
+                "Peer is banned for providing invalid block: {}",
+                peer_id.to_base58()
+            )
+        } else {
+            log::warn!("The block is invalid and could not get peer");
+        }
+    }
 
-                if let Ok(()) = block.is_valid(&state) {
-                    if let Err(error) = state.put_block(&block) {
-                        log::warn!("Put block failed: {error:?}");
-                    }
-                } else if let Some(peer_id) = message.source {
-                    swarm.ban_peer_id(peer_id);
-                    log::warn!(
-                        "Peer is banned for providing invalid block: {}",
-                        peer_id.to_base58()
-                    )
-                } else {
-                    log::warn!("The block is invalid and could not get peer");
+    Ok(())
+}
+
+/// Incoming compact block request handler
+pub async fn compact_block_request(
+    state: Arc<RwLock<State>>,
+    swarm: &mut Swarm<Behaviour>,
+    limits: &mut Limits,
+    peer: PeerId,
+    request: CompactBlockRequest,
+    channel: ResponseChannel<CompactBlockResponse>,
+) -> Result<()> {
+    // The request is dropped without a response
+    if !limits.allow_request(&peer) {
+        log::warn!("Compact block request is throttled: {}", peer.to_base58());
+        return Ok(());
+    }
+
+    let state = state.read().await;
+
+    let request = bincode::deserialize::<BlockTransactionsRequest>(&request.0)
+        .map_err(|error| anyhow!("Failed to deserialize compact block request: {error:?}"))?;
+    log::trace!("Received compact block request: {request:?}");
+
+    let response = match request {
+        BlockTransactionsRequest::Transactions {
+            block_hash,
+            indexes,
+        } => match state.database.get_block_from_hash(block_hash) {
+            Ok(block) => {
+                let transactions = indexes
+                    .iter()
+                    .map(|index| block.transactions.0.get(*index as usize).cloned())
+                    .collect::<Option<Vec<Transaction>>>();
+
+                match transactions {
+                    Some(transactions) => BlockTransactionsResponse::Transactions {
+                        block_hash,
+                        transactions,
+                    },
+                    None => BlockTransactionsResponse::NotFound { block_hash },
                 }
             }
-            TRANSACTION_TOPIC => {
-                let transaction = bincode::deserialize::<Transaction>(&message.data)
-                    .map_err(|error| anyhow!("Failed to deserialize transaction: {error:?}"))?;
+            Err(_) => BlockTransactionsResponse::NotFound { block_hash },
+        },
+        BlockTransactionsRequest::Block { block_hash } => {
+            match state.database.get_block_from_hash(block_hash) {
+                Ok(block) => BlockTransactionsResponse::Block(Box::new(block)),
+                Err(_) => BlockTransactionsResponse::NotFound { block_hash },
+            }
+        }
+    };
+
+    let data = bincode::serialize(&response)
+        .map_err(|error| anyhow!("Failed to serialize compact block response: {error:?}"))?;
+
+    if let Err(error) = swarm
+        .behaviour_mut()
+        .compact_block
+        .send_response(channel, CompactBlockResponse(data))
+    {
+        log::warn!("Send response failed: {error:?}");
+    }
+
+    Ok(())
+}
+
+/// Incoming compact block response handler
+pub async fn compact_block_response(
+    state: Arc<RwLock<State>>,
+    swarm: &mut Swarm<Behaviour>,
+    limits: &mut Limits,
+    peer: PeerId,
+    response: CompactBlockResponse,
+) -> Result<()> {
+    let mut state = state.write().await;
 
-                log::info!(
-                    "New transaction received: {}",
-                    transaction.hash()?.to_base58()
+    let response = bincode::deserialize::<BlockTransactionsResponse>(&response.0)
+        .map_err(|error| anyhow!("Failed to deserialize compact block response: {error:?}"))?;
+
+    let block = match response {
+        BlockTransactionsResponse::Transactions {
+            block_hash,
+            transactions,
+        } => {
+            let mut partial_block = state
+                .take_partial_block(&block_hash)
+                .ok_or_else(|| anyhow!("Unknown compact block: {}", block_hash.to_base58()))?;
+
+            if let Err(error) = partial_block.fill(transactions) {
+                log::warn!("Failed to fill compact block: {error:?}");
+                return send_compact_block_request(
+                    &mut state,
+                    swarm,
+                    limits,
+                    &peer,
+                    BlockTransactionsRequest::Block { block_hash },
                 );
+            }
 
-                if let Ok(()) = transaction.is_valid(&state) {
-                    if let Err(error) = state.put_transaction_mempool(transaction) {
-                        log::warn!("Put transaction failed: {error:?}");
-                    }
-                } else if let Some(peer_id) = message.source {
-                    swarm.ban_peer_id(peer_id);
-                    log::warn!(
-                        "Peer is banned for providing invalid transaction: {}",
-                        peer_id.to_base58()
-                    )
-                } else {
-                    log::warn!("The transaction is invalid and could not get peer");
-                }
+            match complete_partial_block(
+                &mut state,
+                swarm,
+                limits,
+                &peer,
+                block_hash,
+                partial_block,
+            )? {
+                Some(block) => block,
+                None => return Ok(()),
+            }
+        }
+        BlockTransactionsResponse::Block(block) => {
+            let block_hash = block.header.hash()?;
+
+            // Full blocks are accepted only in response to a request
+            if !state.take_block_request(&block_hash) {
+                log::warn!(
+                    "Block was not requested from the peer {}: {}",
+                    peer.to_base58(),
+                    block_hash.to_base58()
+                );
+                return Ok(());
+            }
+
+            state.take_partial_block(&block_hash);
+            *block
+        }
+        BlockTransactionsResponse::NotFound { block_hash } => {
+            state.take_partial_block(&block_hash);
+            state.take_block_request(&block_hash);
+            log::warn!(
+                "Peer does not have the announced block: {}",
+                block_hash.to_base58()
+            );
+            return Ok(());
+        }
+    };
+
+    log::info!("New block received: {}", block.header.height);
+
+    if let Ok(()) = block.is_valid(&state) {
+        if let Err(error) = state.put_block(&block) {
+            log::warn!("Put block failed: {error:?}");
+        }
+    } else {
+        swarm.ban_peer_id(peer);
+        log::warn!(
+            "Peer is banned for providing invalid block: {}",
+            peer.to_base58()
+        )
+    }
+
+    Ok(())
+}
+
+/// Converting a reconstructed block to a full block.
+/// If the Merkle root does not match, the full block is requested from the peer
+fn complete_partial_block(
+    state: &mut State,
+    swarm: &mut Swarm<Behaviour>,
+    limits: &mut Limits,
+    peer: &PeerId,
+    block_hash: Hash,
+    partial_block: PartialBlock,
+) -> Result<Option<Block>> {
+    let block = partial_block.into_block()?;
+
+    if MerkleTree::verify(&block.transactions, block.header.root)? {
+        Ok(Some(block))
+    } else {
+        log::trace!(
+            "Reconstructed block does not match the Merkle root, requesting the full block: {}",
+            block_hash.to_base58()
+        );
+
+        send_compact_block_request(
+            state,
+            swarm,
+            limits,
+            peer,
+            BlockTransactionsRequest::Block { block_hash },
+        )?;
+
+        Ok(None)
+    }
+}
+
+/// Requesting the missing transactions or the full block, the requested full blocks are
+/// recorded so that only they are accepted in the responses
+fn send_compact_block_request(
+    state: &mut State,
+    swarm: &mut Swarm<Behaviour>,
+    limits: &mut Limits,
+    peer: &PeerId,
+    mut request: BlockTransactionsRequest,
+) -> Result<()> {
+    if !limits.start_request(peer) {
+        log::warn!(
+            "Too many outstanding requests to peer: {}",
+            peer.to_base58()
+        );
+        return Ok(());
+    }
+
+    let mut data = bincode::serialize(&request)
+        .map_err(|error| anyhow!("Failed to serialize compact block request: {error:?}"))?;
+
+    // Indexes of too many missing transactions do not fit in a request
+    if let BlockTransactionsRequest::Transactions { block_hash, .. } = request {
+        if data.len() > MAX_REQUEST_SIZE {
+            state.take_partial_block(&block_hash);
+
+            request = BlockTransactionsRequest::Block { block_hash };
+            data = bincode::serialize(&request)
+                .map_err(|error| anyhow!("Failed to serialize compact block request: {error:?}"))?;
+        }
+    }
+
+    if let BlockTransactionsRequest::Block { block_hash } = request {
+        state.put_block_request(block_hash);
+    }
+
+    swarm
+        .behaviour_mut()
+        .compact_block
+        .send_request(peer, CompactBlockRequest(data));
+
+    Ok(())
+}
+
+/// Announcing transactions to the peers that do not know them yet
+pub fn announce_transactions(
+    swarm: &mut Swarm<Behaviour>,
+    inventory: &mut KnownInventory,
+    limits: &mut Limits,
+    hashes: &[Hash],
+) -> Result<()> {
+    let peers = swarm.connected_peers().cloned().collect::<Vec<PeerId>>();
+
+    for peer in peers {
+        for chunk in inventory
+            .filter_unknown(&peer, hashes)
+            .chunks(MAX_INVENTORY_SIZE)
+        {
+            send_transaction_request(
+                swarm,
+                limits,
+                &peer,
+                InventoryRequest::Announce(chunk.to_vec()),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Incoming transaction relay request handler
+pub async fn transaction_request(
+    state: Arc<RwLock<State>>,
+    swarm: &mut Swarm<Behaviour>,
+    inventory: &mut KnownInventory,
+    limits: &mut Limits,
+    peer: PeerId,
+    request: TransactionRequest,
+    channel: ResponseChannel<TransactionResponse>,
+) -> Result<()> {
+    // The request is dropped without a response
+    if !limits.allow_gossip(&peer) {
+        log::trace!("Transaction request is throttled: {}", peer.to_base58());
+        return Ok(());
+    }
+
+    let state = state.read().await;
+
+    let request = bincode::deserialize::<InventoryRequest>(&request.0)
+        .map_err(|error| anyhow!("Failed to deserialize inventory request: {error:?}"))?;
+
+    let (response, unknown) = match request {
+        InventoryRequest::Announce(hashes) => {
+            log::trace!("Received inventory announcement: {}", hashes.len());
+
+            if hashes.len() > MAX_INVENTORY_SIZE {
+                return Err(anyhow!(
+                    "Inventory announcement is too large: {}",
+                    hashes.len()
+                ));
+            }
+
+            inventory.insert(&peer, &hashes);
+
+            let unknown = if state.is_sync {
+                hashes
+                    .into_iter()
+                    .filter(|hash| !state.contains_transaction(hash))
+                    .collect()
+            } else {
+                vec![]
+            };
+
+            (InventoryResponse::Ack, unknown)
+        }
+        InventoryRequest::Get(hashes) => {
+            log::trace!("Received transactions request: {}", hashes.len());
+
+            let transactions = hashes
+                .iter()
+                .take(MAX_INVENTORY_SIZE)
+                .filter_map(|hash| state.get_transaction_mempool(hash).cloned())
+                .collect();
+
+            inventory.insert(&peer, &hashes);
+
+            (InventoryResponse::Transactions(transactions), vec![])
+        }
+    };
+
+    let data = bincode::serialize(&response)
+        .map_err(|error| anyhow!("Failed to serialize inventory response: {error:?}"))?;
+
+    if let Err(error) = swarm
+        .behaviour_mut()
+        .transaction
+        .send_response(channel, TransactionResponse(data))
+    {
+        log::warn!("Send response failed: {error:?}");
+    }
+
+    if !unknown.is_empty() {
+        send_transaction_request(swarm, limits, &peer, InventoryRequest::Get(unknown))?;
+    }
+
+    Ok(())
+}
+
+/// Incoming transaction relay response handler
+pub async fn transaction_response(
+    state: Arc<RwLock<State>>,
+    swarm: &mut Swarm<Behaviour>,
+    inventory: &mut KnownInventory,
+    limits: &mut Limits,
+    peer: PeerId,
+    response: TransactionResponse,
+) -> Result<()> {
+    let mut state = state.write().await;
+
+    let transactions = match bincode::deserialize::<InventoryResponse>(&response.0)
+        .map_err(|error| anyhow!("Failed to deserialize inventory response: {error:?}"))?
+    {
+        InventoryResponse::Ack => return Ok(()),
+        InventoryResponse::Transactions(transactions) => transactions,
+    };
+
+    let mut accepted = vec![];
+
+    for transaction in transactions {
+        let hash = transaction.hash()?;
+        inventory.insert(&peer, &[hash]);
+
+        if state.contains_transaction(&hash) {
+            continue;
+        }
+
+        log::info!("New transaction received: {}", hash.to_base58());
+
+        if let Ok(()) = transaction.is_valid(&state) {
+            if let Err(error) = state.put_transaction_mempool(transaction) {
+                log::warn!("Put transaction failed: {error:?}");
+            } else {
+                accepted.push(hash);
             }
-            _ => {}
+        } else {
+            swarm.ban_peer_id(peer);
+            log::warn!(
+                "Peer is banned for providing invalid transaction: {}",
+                peer.to_base58()
+            );
+            break;
         }
     }
 
+    announce_transactions(swarm, inventory, limits, &accepted)
+}
+
+/// Handler of the transaction submitted by RPC
+pub async fn submitted_transaction_handler(
+    state: Arc<RwLock<State>>,
+    swarm: &mut Swarm<Behaviour>,
+    inventory: &mut KnownInventory,
+    limits: &mut Limits,
+    transaction: Transaction,
+) -> Result<()> {
+    let mut state = state.write().await;
+
+    let hash = transaction.hash()?;
+    if state.contains_transaction(&hash) {
+        return Ok(());
+    }
+
+    transaction.is_valid(&state)?;
+    state.put_transaction_mempool(transaction)?;
+    log::info!("New transaction submitted: {}", hash.to_base58());
+
+    announce_transactions(swarm, inventory, limits, &[hash])
+}
+
+fn send_transaction_request(
+    swarm: &mut Swarm<Behaviour>,
+    limits: &mut Limits,
+    peer: &PeerId,
+    request: InventoryRequest,
+) -> Result<()> {
+    if !limits.start_request(peer) {
+        log::warn!(
+            "Too many outstanding requests to peer: {}",
+            peer.to_base58()
+        );
+        return Ok(());
+    }
+
+    let data = bincode::serialize(&request)
+        .map_err(|error| anyhow!("Failed to serialize inventory request: {error:?}"))?;
+
+    swarm
+        .behaviour_mut()
+        .transaction
+        .send_request(peer, TransactionRequest(data));
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block::Header,
+        transaction::{Data, Transactions},
+    };
+
+    fn block(attachment: usize) -> Block {
+        let data = Data::Transfer {
+            recipient: EMPTY_ADDRESS,
+            amount: 1,
+            attachment: "a".repeat(attachment),
+        };
+
+        let mut transactions = Transactions::default();
+        transactions.push(Transaction::new(
+            0,
+            EMPTY_ADDRESS,
+            EMPTY_PUBLIC_KEY,
+            1,
+            MIN_FEE,
+            0,
+            TRANSACTION_LIFETIME_BLOCKS,
+            data,
+        ));
+
+        Block {
+            header: Header::new(
+                0,
+                1,
+                0,
+                EMPTY_HASH,
+                EMPTY_ADDRESS,
+                EMPTY_PUBLIC_KEY,
+                0,
+                EMPTY_HASH,
+                1,
+            ),
+            transactions,
+        }
+    }
+
+    #[test]
+    fn collect_blocks_limit() {
+        let blocks = match collect_blocks((0..3).map(|_| block(MAX_TRANSMIT_SIZE / 3))).unwrap() {
+            SyncResponse::Blocks(blocks) => blocks,
+            _ => panic!("Blocks are not collected"),
+        };
+        assert_eq!(blocks.len(), 2);
+
+        // The encoded response with the version byte fits in the limit of the reader
+        let size = 1 + bincode::serialized_size(&SyncResponse::Blocks(blocks)).unwrap() as usize;
+        assert!(size <= MAX_TRANSMIT_SIZE);
+    }
+
+    #[test]
+    fn refuse_oversized_block() {
+        // The requester could not read a response with the block
+        let response = collect_blocks((0..2).map(|_| block(MAX_TRANSMIT_SIZE))).unwrap();
+        assert!(matches!(response, SyncResponse::Refused(_)));
+    }
+}
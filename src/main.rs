@@ -1,31 +1,48 @@
 use async_std::{
-    stream,
+    channel, future, stream,
     sync::{Arc, RwLock},
 };
-use base58::ToBase58;
-use clap::Parser;
+use base58::{FromBase58, ToBase58};
+use clap::{Parser, Subcommand};
 use gem_node::{
+    account::Account,
+    block::genesis,
     constants::*,
     futures_handler::*,
     pow::miner,
     primitive::*,
-    rpc::{Rpc, RpcHandler},
-    state::State,
-    swarm::{self, behaviour::BehaviourEvent},
-    wallet,
+    rpc::{auth::authorization, client::RpcClient, Rpc, RpcHandler},
+    state::{archive, snapshot::Snapshot, storage::RocksDbConfig, Database, State},
+    swarm::{
+        self,
+        behaviour::BehaviourEvent,
+        inventory::KnownInventory,
+        limits::{Limits, Metrics},
+    },
+    transaction::Data,
+    wallet::{self, builder, KdfParams, MiningKey, PasswordSource},
+};
+use jsonrpc_http_server::{
+    jsonrpc_core::{serde_json, IoHandler},
+    ServerBuilder,
 };
-use jsonrpc_http_server::{jsonrpc_core::IoHandler, ServerBuilder};
 use libp2p::{
     futures::{select, FutureExt, StreamExt},
     gossipsub, identify, mdns, request_response,
     swarm::SwarmEvent,
 };
 use log::LevelFilter;
-use std::{error::Error, time::Duration};
+use signal_hook::{
+    consts::{SIGINT, SIGTERM},
+    iterator::Signals,
+};
+use std::{error::Error, fs, net::SocketAddr, path::Path, time::Duration};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(long, default_value_t = String::from("./gem"))]
     directory: String,
     #[arg(long, value_enum, default_value_t = Network::Testnet)]
@@ -34,12 +51,148 @@ struct Args {
     rpc_address: String,
     #[arg(long, default_value_t = 31337)]
     rpc_port: u16,
+    #[arg(long, default_value_t = String::new())]
+    rpc_token_file: String,
     #[arg(long, default_value_t = false)]
     generate_keys: bool,
     #[arg(long, default_value_t = String::new())]
     import_secret_key: String,
+    #[arg(long, default_value_t = String::new())]
+    recover_mnemonic: String,
+    #[arg(long, default_value_t = false)]
+    new_account: bool,
+    #[arg(long, default_value_t = false)]
+    list_accounts: bool,
+    #[arg(long, default_value_t = String::new())]
+    label: String,
+    #[arg(long, default_value_t = 0)]
+    account: usize,
+    #[arg(long)]
+    password_fd: Option<u32>,
+    #[arg(long, default_value_t = String::new())]
+    password_file: String,
+    #[arg(long, default_value_t = String::new())]
+    password_env: String,
+    /// Start mining with the locked wallet, it is unlocked by gem_unlockWallet
+    #[arg(long, default_value_t = false)]
+    wallet_locked: bool,
+    #[arg(long, default_value_t = false)]
+    change_password: bool,
+    #[arg(long, default_value_t = String::new())]
+    new_password_file: String,
+    #[arg(long, default_value_t = String::new())]
+    new_password_env: String,
+    #[arg(long, default_value_t = false)]
+    reencrypt_wallet: bool,
+    /// Argon2 memory cost of the wallet encryption in KiB
+    #[arg(long)]
+    kdf_memory: Option<u32>,
+    #[arg(long)]
+    kdf_iterations: Option<u32>,
+    #[arg(long)]
+    kdf_parallelism: Option<u32>,
     #[arg(long, default_value_t = false)]
     mining: bool,
+    #[arg(long, default_value_t = String::new())]
+    database_config: String,
+    #[arg(long)]
+    prune: Option<u64>,
+    #[arg(long, default_value_t = false)]
+    reindex: bool,
+    #[arg(long)]
+    verify_chain: Option<u64>,
+    #[arg(long, default_value_t = String::new())]
+    export_snapshot: String,
+    #[arg(long, default_value_t = String::new())]
+    import_snapshot: String,
+    #[arg(long, default_value_t = String::new())]
+    snapshot_hash: String,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Export blocks to a file, continuing an existing file
+    ExportBlocks {
+        #[arg(long, default_value_t = 0)]
+        from: u64,
+        #[arg(long)]
+        to: Option<u64>,
+        #[arg(long)]
+        out: String,
+    },
+    /// Import blocks from a file created by export-blocks
+    ImportBlocks { file: String },
+    /// Create transactions of the wallet account and submit them to the running node
+    Wallet {
+        #[command(subcommand)]
+        command: WalletCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum WalletCommand {
+    /// Transfer coins to the address
+    Send {
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        amount: u64,
+        #[arg(long, default_value_t = String::new())]
+        attachment: String,
+        #[command(flatten)]
+        options: TransactionOptions,
+    },
+    /// Replace the public key of the account with the key of another wallet account or the given key
+    RotateKey {
+        #[arg(long)]
+        new_account: Option<usize>,
+        #[arg(long)]
+        new_public_key: Option<String>,
+        #[command(flatten)]
+        options: TransactionOptions,
+    },
+    /// Sign the unsigned transaction file offline
+    Sign {
+        file: String,
+        #[arg(long)]
+        out: String,
+    },
+    /// Submit the signed transaction file
+    Broadcast { file: String },
+    /// Track the balance and the history of the address or the public key on the node
+    Watch {
+        #[arg(long)]
+        address: Option<String>,
+        #[arg(long)]
+        public_key: Option<String>,
+        #[arg(long, default_value_t = String::new())]
+        label: String,
+        /// Rescan the blocks from the height for the history before the address was watched
+        #[arg(long)]
+        rescan_from: Option<u64>,
+    },
+    /// Rebuild the history of the watched addresses from the height
+    Rescan {
+        #[arg(long)]
+        from_height: u64,
+    },
+    /// Print the balances and the history of the watched addresses
+    Watched,
+}
+
+#[derive(clap::Args, Debug)]
+struct TransactionOptions {
+    #[arg(long, default_value_t = MIN_FEE)]
+    fee: u64,
+    /// Address of the account if its public key was rotated
+    #[arg(long)]
+    from: Option<String>,
+    /// Public key of the sender, used instead of the wallet account
+    #[arg(long)]
+    public_key: Option<String>,
+    /// Write the unsigned transaction to the file instead of signing and submitting it
+    #[arg(long)]
+    out: Option<String>,
 }
 
 #[async_std::main]
@@ -53,57 +206,290 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // Parsing command line arguments
     let args = Args::parse();
 
+    let wallet_path = format!("{}/wallet.dat", args.directory);
+    let password_source = if let Some(fd) = args.password_fd {
+        PasswordSource::Fd(fd)
+    } else if !args.password_file.is_empty() {
+        PasswordSource::File(args.password_file.clone())
+    } else if !args.password_env.is_empty() {
+        PasswordSource::Env(args.password_env.clone())
+    } else {
+        PasswordSource::Prompt
+    };
+
+    let rpc_token = if args.rpc_token_file.is_empty() {
+        None
+    } else {
+        let token = fs::read_to_string(&args.rpc_token_file)
+            .map_err(|error| format!("Failed to read {}: {error}", args.rpc_token_file))?;
+        Some(token.trim().to_string())
+    };
+
+    // Wallet commands only talk to the running node
+    if let Some(Command::Wallet { ref command }) = args.command {
+        wallet_command(&args, &wallet_path, &password_source, rpc_token, command)?;
+        std::process::exit(0);
+    }
+
     // Initializing blockchain state
     let db_path = format!("{}/data", args.directory);
-    let state = Arc::new(RwLock::new(State::new(&db_path, args.network)?));
+    let db_config = if args.database_config.is_empty() {
+        RocksDbConfig::default()
+    } else {
+        RocksDbConfig::load(&args.database_config)?
+    };
 
-    // Generating or importing keys
-    let wallet_path = format!("{}/wallet.dat", args.directory);
-    if args.generate_keys {
-        let (secret_key, _) = wallet::generate();
-        wallet::save(&wallet_path, secret_key)?;
+    // Importing a snapshot into an empty database, only with a trusted hash of its last block
+    if !args.import_snapshot.is_empty() {
+        if args.snapshot_hash.is_empty() {
+            return Err("Snapshot import requires --snapshot-hash of the last block".into());
+        }
+
+        let bytes = args
+            .snapshot_hash
+            .from_base58()
+            .map_err(|error| format!("Base58 decode failed: {error:?}"))?;
+        let trusted_hash = Hash::try_from(bytes.as_slice())?;
+
+        Snapshot::load(&args.import_snapshot)?
+            .import(&Database::open(&db_path, &db_config)?, trusted_hash)?;
+    }
+
+    let mut state = State::from_database(Database::open(&db_path, &db_config)?, args.network)?;
+    if let Some(depth) = args.prune {
+        state.set_prune_depth(depth)?;
+        log::info!("Pruning of block transactions deeper than {depth} blocks is enabled");
+    }
+
+    // Rebuilding the state from the stored blocks
+    if args.reindex {
+        state.reindex()?;
+    }
+
+    // Verifying the last blocks
+    if let Some(count) = args.verify_chain {
+        if let Err(error) = state.verify_chain(count) {
+            log::error!("Chain verification failed: {error}");
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
+    // Exporting or importing blocks
+    match args.command {
+        Some(Command::ExportBlocks { from, to, ref out }) => {
+            archive::export_blocks(&state, from, to, out)?;
+            std::process::exit(0);
+        }
+        Some(Command::ImportBlocks { ref file }) => {
+            archive::import_blocks(&mut state, file)?;
+            state.close()?;
+            std::process::exit(0);
+        }
+        Some(Command::Wallet { .. }) | None => {}
+    }
+
+    // Exporting a snapshot of the state at the last block
+    if !args.export_snapshot.is_empty() {
+        Snapshot::from_state(&state)?.save(&args.export_snapshot)?;
+        log::info!(
+            "Snapshot is exported at height {}",
+            state.last_header.height
+        );
+        std::process::exit(0);
+    }
+
+    // Generating, recovering or importing keys
+    let height = state.last_header.height;
+    let kdf_params = |kdf: KdfParams| {
+        KdfParams::new(
+            args.kdf_memory.unwrap_or(kdf.m_cost),
+            args.kdf_iterations.unwrap_or(kdf.t_cost),
+            args.kdf_parallelism.unwrap_or(kdf.p_cost),
+        )
+    };
+    if args.generate_keys || !args.recover_mnemonic.is_empty() {
+        let mnemonic = if args.generate_keys {
+            let mnemonic = wallet::hd::generate_mnemonic()?;
+            println!("Mnemonic phrase: {mnemonic}");
+            mnemonic
+        } else {
+            wallet::hd::parse_mnemonic(&args.recover_mnemonic)?
+        };
+
+        let mut wallet = wallet::Wallet::from_mnemonic(&mnemonic, &args.label, height);
+        wallet.set_kdf(kdf_params(wallet.kdf()))?;
+        let (_, public_key) = wallet.keypair(0)?;
+        println!("Public key: {}", public_key.to_base58());
+        println!(
+            "Address: {}",
+            Account::from_public_key(public_key, args.network).address
+        );
+
+        wallet::save(&wallet_path, &password_source.read()?, &wallet)?;
+        std::process::exit(0);
+    } else if !args.import_secret_key.is_empty() || args.new_account {
+        let password = password_source.read()?;
+
+        // Keys are added to the existing wallet
+        let mut wallet = if Path::new(&wallet_path).exists() {
+            wallet::load(&wallet_path, &password)?
+        } else {
+            let mut wallet = wallet::Wallet::default();
+            wallet.set_kdf(kdf_params(wallet.kdf()))?;
+            wallet
+        };
+
+        let index = if args.new_account {
+            wallet.add_account(&args.label, height)?
+        } else {
+            let (secret_key, _) = wallet::import(&args.import_secret_key)?;
+            wallet.import_key(secret_key, &args.label, height)
+        };
+        let (_, public_key) = wallet.keypair(index)?;
+        println!("Account {index} public key: {}", public_key.to_base58());
+        println!(
+            "Address: {}",
+            Account::from_public_key(public_key, args.network).address
+        );
+
+        wallet::save(&wallet_path, &password, &wallet)?;
+        std::process::exit(0);
+    } else if args.change_password || args.reencrypt_wallet {
+        let password = password_source.read()?;
+        let mut wallet = wallet::load(&wallet_path, &password)?;
+        wallet.set_kdf(kdf_params(wallet.kdf()))?;
+
+        // Nodes without a terminal read the new password like the current one
+        let password = if !args.change_password {
+            password
+        } else if !args.new_password_file.is_empty() {
+            PasswordSource::File(args.new_password_file.clone()).read()?
+        } else if !args.new_password_env.is_empty() {
+            PasswordSource::Env(args.new_password_env.clone()).read()?
+        } else {
+            wallet::prompt_new_password()?
+        };
+        wallet::save(&wallet_path, &password, &wallet)?;
+
+        let kdf = wallet.kdf();
+        println!(
+            "Wallet is encrypted with Argon2id m_cost={} KiB, t_cost={}, p_cost={}",
+            kdf.m_cost, kdf.t_cost, kdf.p_cost
+        );
         std::process::exit(0);
-    } else if !args.import_secret_key.is_empty() {
-        let (secret_key, _) = wallet::import(&args.import_secret_key)?;
-        wallet::save(&wallet_path, secret_key)?;
+    } else if args.list_accounts {
+        let wallet = wallet::load(&wallet_path, &password_source.read()?)?;
+
+        for (index, account) in wallet.accounts().iter().enumerate() {
+            let (_, public_key) = wallet.keypair(index)?;
+            println!(
+                "{index}: {} {} {} (created at height {})",
+                account.label,
+                Account::from_public_key(public_key, args.network).address,
+                public_key.to_base58(),
+                account.creation_height
+            );
+        }
         std::process::exit(0);
     }
 
-    let (mut secret_key, mut public_key) = (EMPTY_SECRET_KEY, EMPTY_PUBLIC_KEY);
+    let state = Arc::new(RwLock::new(state));
 
-    // If the node mines blocks, then we load the wallet
-    if args.mining {
-        (secret_key, public_key) = wallet::load(&wallet_path)?;
+    let mining_key = Arc::new(RwLock::new(MiningKey::new(&wallet_path, args.account)));
+
+    // If the node mines blocks, then we unlock the wallet
+    if args.mining && args.wallet_locked {
+        log::info!("Wallet is locked, mining starts after gem_unlockWallet");
+    } else if args.mining {
+        let public_key = mining_key
+            .write()
+            .await
+            .unlock(&password_source.read()?, None)?;
         log::info!("Miner public key: {}", public_key.to_base58());
     } else {
         log::warn!("Block mining is disabled by default");
     }
 
+    let metrics = Arc::new(Metrics::default());
+
     // Initializing libp2p Swarm
-    let mut swarm = swarm::init().await?;
+    let mut swarm = swarm::init(metrics.clone()).await?;
     swarm.listen_on("/ip4/0.0.0.0/tcp/0".parse()?)?;
+    #[cfg(feature = "quic")]
+    swarm.listen_on("/ip4/0.0.0.0/udp/0/quic-v1".parse()?)?;
+
+    // Shutdown is triggered by SIGINT, SIGTERM or the RPC
+    let (shutdown_sender, shutdown_receiver) = channel::bounded(1);
+    let (transaction_sender, transaction_receiver) = channel::bounded(64);
+
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+    let signal_sender = shutdown_sender.clone();
+    std::thread::spawn(move || {
+        for signal in signals.forever() {
+            log::info!("Received signal: {signal}");
+            let _ = signal_sender.try_send(());
+        }
+    });
 
     let mut io = IoHandler::default();
-    let rpc = RpcHandler::new(state.clone());
+    let rpc = RpcHandler::new(
+        state.clone(),
+        metrics.clone(),
+        mining_key.clone(),
+        transaction_sender,
+        shutdown_sender,
+    );
     io.extend_with(rpc.to_delegate());
 
-    let rpc_addr = format!("{}:{}", args.rpc_address, args.rpc_port);
-    let _server = ServerBuilder::new(io)
-        .threads(1)
-        .start_http(&rpc_addr.parse()?)?;
+    // The RPC controls the wallet and the node, other hosts need the token
+    let rpc_addr: SocketAddr = format!("{}:{}", args.rpc_address, args.rpc_port).parse()?;
+    let mut builder = ServerBuilder::new(io).threads(1);
+    match rpc_token {
+        Some(token) => builder = builder.request_middleware(authorization(token)),
+        None if !rpc_addr.ip().is_loopback() => {
+            return Err("RPC on a non-loopback address requires --rpc-token-file".into())
+        }
+        None => {}
+    }
+    let server = builder.start_http(&rpc_addr)?;
 
     let mut sync_interval = stream::interval(Duration::from_secs(15));
+    let mut inventory = KnownInventory::default();
+    let mut limits = Limits::new(metrics);
+    let mut failure = None;
 
     loop {
+        let keypair = mining_key.write().await.keypair();
+        let (secret_key, public_key) = keypair.unwrap_or((EMPTY_SECRET_KEY, EMPTY_PUBLIC_KEY));
+        let mining = args.mining && keypair.is_some();
+
         select! {
-            _ = sync_interval.next().fuse() => if let Err(error) = sync_blocks(state.clone(), &mut swarm).await {
+            _ = shutdown_receiver.recv().fuse() => break,
+            transaction = transaction_receiver.recv().fuse() => if let Ok(transaction) = transaction {
+                if let Err(error) = submitted_transaction_handler(state.clone(), &mut swarm, &mut inventory, &mut limits, transaction).await {
+                    log::warn!("Submitted transaction rejected: {error:?}");
+                }
+            },
+            _ = sync_interval.next().fuse() => if let Err(error) = sync_blocks(state.clone(), &mut swarm, &mut limits).await {
                 log::error!("Sync failed: {error:?}");
             },
-            result = miner::trying(state.clone(), &secret_key, &public_key, args.network, args.mining).fuse() => mining_handler(state.clone(), &mut swarm, result).await?,
+            result = miner::trying(state.clone(), &secret_key, &public_key, args.network, mining).fuse() => if let Err(error) = mining_handler(state.clone(), &mut swarm, result).await {
+                // The database is still closed cleanly below
+                log::error!("Mining failed, stopping the node: {error:?}");
+                failure = Some(error);
+                break;
+            },
             event = swarm.select_next_some() => match event {
                 SwarmEvent::NewListenAddr { address, .. } => {
                     log::info!("Swarm listening on {address:?}");
                 },
+                SwarmEvent::ConnectionClosed { peer_id, num_established, .. } => {
+                    if num_established == 0 {
+                        inventory.remove_peer(&peer_id);
+                        limits.remove_peer(&peer_id);
+                    }
+                },
                 SwarmEvent::Behaviour(BehaviourEvent::Identify(identify::Event::Received{ peer_id, info })) => {
                     if info.protocol_version != swarm::protocol_version() {
                         swarm.ban_peer_id(peer_id);
@@ -123,21 +509,255 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     }
                 },
                 SwarmEvent::Behaviour(BehaviourEvent::RequestResponse(request_response::Event::Message { peer, message })) => match message {
-                    request_response::Message::Request { request, channel, .. } => if let Err(error) = sync_request(state.clone(), &mut swarm, request, channel).await {
+                    request_response::Message::Request { request, channel, .. } => if let Err(error) = sync_request(state.clone(), &mut swarm, &mut limits, peer, request, channel).await {
                         log::error!("Sync request failed: {error:?}");
                     },
-                    request_response::Message::Response { response, .. } => if let Err(error) = sync_response(state.clone(), &mut swarm, peer, response).await {
-                        log::error!("Sync response failed: {error:?}");
+                    request_response::Message::Response { response, .. } => {
+                        limits.finish_request(&peer);
+
+                        if let Err(error) = sync_response(state.clone(), &mut swarm, &mut limits, peer, response).await {
+                            log::error!("Sync response failed: {error:?}");
+                        }
+                    },
+                },
+                SwarmEvent::Behaviour(BehaviourEvent::CompactBlock(request_response::Event::Message { peer, message })) => match message {
+                    request_response::Message::Request { request, channel, .. } => if let Err(error) = compact_block_request(state.clone(), &mut swarm, &mut limits, peer, request, channel).await {
+                        log::error!("Compact block request failed: {error:?}");
+                    },
+                    request_response::Message::Response { response, .. } => {
+                        limits.finish_request(&peer);
+
+                        if let Err(error) = compact_block_response(state.clone(), &mut swarm, &mut limits, peer, response).await {
+                            log::error!("Compact block response failed: {error:?}");
+                        }
+                    },
+                },
+                SwarmEvent::Behaviour(BehaviourEvent::Transaction(request_response::Event::Message { peer, message })) => match message {
+                    request_response::Message::Request { request, channel, .. } => if let Err(error) = transaction_request(state.clone(), &mut swarm, &mut inventory, &mut limits, peer, request, channel).await {
+                        log::error!("Transaction request failed: {error:?}");
+                    },
+                    request_response::Message::Response { response, .. } => {
+                        limits.finish_request(&peer);
+
+                        if let Err(error) = transaction_response(state.clone(), &mut swarm, &mut inventory, &mut limits, peer, response).await {
+                            log::error!("Transaction response failed: {error:?}");
+                        }
                     },
                 },
+                SwarmEvent::Behaviour(BehaviourEvent::RequestResponse(request_response::Event::OutboundFailure { peer, error, .. }))
+                | SwarmEvent::Behaviour(BehaviourEvent::CompactBlock(request_response::Event::OutboundFailure { peer, error, .. }))
+                | SwarmEvent::Behaviour(BehaviourEvent::Transaction(request_response::Event::OutboundFailure { peer, error, .. })) => {
+                    limits.finish_request(&peer);
+                    log::warn!("Request to peer {} failed: {error:?}", peer.to_base58());
+                },
                 SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(gossipsub::Event::Message {
+                    propagation_source,
                     message,
                     ..
-                })) => if let Err(error) = gossipsub_handler(state.clone(), &mut swarm, message).await {
+                })) => if let Err(error) = gossipsub_handler(state.clone(), &mut swarm, &mut limits, propagation_source, message).await {
                     log::error!("Gossipsub failed: {error:?}");
                 },
                 _ => {}
             }
         }
     }
+
+    log::info!("Shutting down");
+
+    // Block mining stopped with the loop, no new RPC requests are accepted
+    server.close();
+
+    // Waiting for the block being written and persisting the database
+    let mut state = state.write().await;
+    state.close()?;
+
+    // Closing the connections to peers
+    let peers = swarm.connected_peers().cloned().collect::<Vec<_>>();
+    for peer_id in peers {
+        let _ = swarm.disconnect_peer_id(peer_id);
+    }
+
+    let _ = future::timeout(Duration::from_secs(5), async {
+        while swarm.network_info().num_peers() > 0 {
+            swarm.select_next_some().await;
+        }
+    })
+    .await;
+
+    log::info!("Node is stopped");
+
+    match failure {
+        Some(error) => Err(error.into()),
+        None => Ok(()),
+    }
+}
+
+/// Building, signing and submitting transactions of the wallet account
+fn wallet_command(
+    args: &Args,
+    wallet_path: &str,
+    password_source: &PasswordSource,
+    rpc_token: Option<String>,
+    command: &WalletCommand,
+) -> Result<(), Box<dyn Error>> {
+    let client =
+        RpcClient::new(&format!("{}:{}", args.rpc_address, args.rpc_port)).with_token(rpc_token);
+
+    // Unsigned transactions of a known public key and broadcasting do not need the secret keys
+    let needs_wallet = match command {
+        WalletCommand::Send { options, .. } => options.public_key.is_none(),
+        WalletCommand::RotateKey {
+            new_account,
+            options,
+            ..
+        } => options.public_key.is_none() || new_account.is_some(),
+        WalletCommand::Sign { .. } => true,
+        WalletCommand::Broadcast { .. }
+        | WalletCommand::Watch { .. }
+        | WalletCommand::Rescan { .. }
+        | WalletCommand::Watched => false,
+    };
+    let wallet = if needs_wallet {
+        Some(wallet::load(wallet_path, &password_source.read()?)?)
+    } else {
+        None
+    };
+    let wallet = || wallet.as_ref().ok_or("Wallet is not loaded");
+
+    let (options, data) = match command {
+        WalletCommand::Send {
+            to,
+            amount,
+            attachment,
+            options,
+        } => {
+            let data = Data::Transfer {
+                recipient: address_from_str(to, args.network)?,
+                amount: *amount,
+                attachment: attachment.clone(),
+            };
+            (options, data)
+        }
+        WalletCommand::RotateKey {
+            new_account,
+            new_public_key,
+            options,
+        } => {
+            let public_key = match (new_account, new_public_key) {
+                (Some(index), None) => wallet()?.keypair(*index)?.1,
+                (None, Some(public_key)) => builder::parse_public_key(public_key)?,
+                _ => return Err("Either --new-account or --new-public-key is required".into()),
+            };
+            (options, Data::RotatePublicKey { public_key })
+        }
+        WalletCommand::Sign { file, out } => {
+            let wallet = wallet()?;
+
+            let mut transaction = builder::load_transaction(file)?;
+            println!("{}", builder::describe(&transaction));
+
+            if transaction.chain_id != genesis::chain_id(args.network)? {
+                return Err(format!(
+                    "Transaction chain id {} is not the chain id of {:?}",
+                    transaction.chain_id, args.network
+                )
+                .into());
+            }
+
+            // The transaction is signed by the wallet account of its sender public key
+            let index = wallet
+                .find_account(transaction.sender_public_key)?
+                .ok_or("Sender public key of the transaction does not belong to the wallet")?;
+            println!(
+                "Signing with account {index} ({})",
+                wallet.accounts()[index].label
+            );
+
+            let (secret_key, _) = wallet.keypair(index)?;
+            transaction.sign(&secret_key)?;
+            builder::save_transaction(out, &transaction)?;
+
+            println!("Signed transaction is saved: {out}");
+            return Ok(());
+        }
+        WalletCommand::Broadcast { file } => {
+            let transaction = builder::load_transaction(file)?;
+            transaction.signature_verify()?;
+
+            let hash = client.send_transaction(&hex::encode(bincode::serialize(&transaction)?))?;
+            println!("Transaction submitted: {hash}");
+            return Ok(());
+        }
+        WalletCommand::Watch {
+            address,
+            public_key,
+            label,
+            rescan_from,
+        } => {
+            let address = match (address, public_key) {
+                (Some(address), None) => address_from_str(address, args.network)?,
+                (None, Some(public_key)) => {
+                    let public_key = builder::parse_public_key(public_key)?;
+                    Account::from_public_key(public_key, args.network).address
+                }
+                _ => return Err("Either --address or --public-key is required".into()),
+            };
+
+            let address = address.to_string();
+            client.watch_address(&address, label)?;
+            println!("Address is watched: {address}");
+
+            if let Some(height) = rescan_from {
+                let count = client.rescan(*height)?;
+                println!("Rescanned blocks: {count}");
+            }
+            return Ok(());
+        }
+        WalletCommand::Rescan { from_height } => {
+            let count = client.rescan(*from_height)?;
+            println!("Rescanned blocks: {count}");
+            return Ok(());
+        }
+        WalletCommand::Watched => {
+            let watched = client.get_watched()?;
+            println!("{}", serde_json::to_string_pretty(&watched)?);
+            return Ok(());
+        }
+    };
+
+    let (secret_key, public_key) = match options.public_key {
+        Some(ref public_key) => (None, builder::parse_public_key(public_key)?),
+        None => {
+            let (secret_key, public_key) = wallet()?.keypair(args.account)?;
+            (Some(secret_key), public_key)
+        }
+    };
+    let sender = match options.from {
+        Some(ref from) => address_from_str(from, args.network)?,
+        None => Account::from_public_key(public_key, args.network).address,
+    };
+
+    let mut transaction = builder::build_transaction(
+        &client,
+        genesis::chain_id(args.network)?,
+        sender,
+        public_key,
+        options.fee,
+        data,
+    )?;
+
+    // The unsigned transaction is signed offline by `wallet sign`
+    if let Some(ref out) = options.out {
+        builder::save_transaction(out, &transaction)?;
+        println!("Unsigned transaction is saved: {out}");
+        return Ok(());
+    }
+
+    let secret_key = secret_key.ok_or("Transaction of --public-key must be saved with --out")?;
+    transaction.sign(&secret_key)?;
+
+    let hash = client.send_transaction(&hex::encode(bincode::serialize(&transaction)?))?;
+    println!("Transaction submitted: {hash}");
+
+    Ok(())
 }
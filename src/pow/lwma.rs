@@ -4,6 +4,7 @@ use anyhow::{anyhow, Result};
 const T: u64 = 15000;
 const N: u64 = LWMA_NUMBER_BLOCKS;
 
+#[derive(Clone)]
 pub struct Lwma1 {
     k: u64,
     pow_limit: U256,
@@ -52,7 +52,8 @@ pub async fn trying(
     // TODO: Calculating the block reward
 
     // Preparing the block header
-    let mut header = Header::new(
+    let header = Header::new(
+        state.chain_id(),
         state.last_header.height + 1,
         timestamp,
         state.last_header.hash()?,
@@ -62,24 +63,28 @@ pub async fn trying(
         root_hash,
         transactions.len(),
     );
+    let mut block = Block {
+        header,
+        transactions,
+    };
+
+    // Committing the state after applying the block
+    block.header.state_root = state.calculate_state_root(&block)?;
 
     // Get a random nonce
     let mut rng = rand::thread_rng();
     let nonce: u64 = rng.gen();
 
-    // Add a nonce and calculate the PoW hash
-    header.nonce = nonce;
-    header.pow_hash(&randomx_vm)?;
-    let value = U256::from(header.pow_hash.as_slice());
+    // Add a nonce and the target, then calculate the PoW hash committing to both
+    block.header.nonce = nonce;
+    block.header.n_bits = state.lwma1.get_target_u32();
+    block.header.pow_hash(&randomx_vm)?;
+    let value = U256::from(block.header.pow_hash.as_slice());
 
     if value <= state.lwma1.get_target() {
-        header.n_bits = state.lwma1.get_target_u32();
-        header.sign(secret_key)?;
+        block.header.sign(secret_key)?;
 
-        Ok(Block {
-            header,
-            transactions,
-        })
+        Ok(block)
     } else {
         Err(anyhow!("Value does not satisfy the target"))
     }
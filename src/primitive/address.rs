@@ -0,0 +1,145 @@
+use crate::{constants::*, primitive::*};
+use anyhow::{anyhow, Error, Result};
+use base58::{FromBase58, ToBase58};
+use blake2::Digest;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+    str::FromStr,
+};
+
+/// Account address: the prefix, the network byte and the hash of the first public key.
+/// The text form is base58 with a checksum
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Address(pub [u8; ADDRESS_LENGTH]);
+
+impl Address {
+    /// Checksum of the address, the first bytes of its Blake2b hash
+    fn checksum(&self) -> [u8; ADDRESS_CHECKSUM_LENGTH] {
+        let hash = Blake2b256::digest(self.0);
+
+        let mut checksum = [0u8; ADDRESS_CHECKSUM_LENGTH];
+        checksum.copy_from_slice(&hash[..ADDRESS_CHECKSUM_LENGTH]);
+        checksum
+    }
+}
+
+impl Deref for Address {
+    type Target = [u8; ADDRESS_LENGTH];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Address {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl AsRef<[u8]> for Address {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl TryFrom<&[u8]> for Address {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        Ok(Self(bytes.try_into().map_err(|_| {
+            anyhow!("Invalid address length: {}", bytes.len())
+        })?))
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut bytes = self.0.to_vec();
+        bytes.extend_from_slice(&self.checksum());
+        write!(f, "{}", bytes.to_base58())
+    }
+}
+
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Address({self})")
+    }
+}
+
+/// Parsing the base58 address with the checksum, the network is checked by `check_address`
+impl FromStr for Address {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let bytes = text
+            .from_base58()
+            .map_err(|error| anyhow!("Base58 decode failed: {error:?}"))?;
+
+        if bytes.len() != ADDRESS_LENGTH + ADDRESS_CHECKSUM_LENGTH {
+            return Err(anyhow!("Invalid address length: {}", bytes.len()));
+        }
+        let (address, checksum) = bytes.split_at(ADDRESS_LENGTH);
+        let address = Address::try_from(address)?;
+
+        if address.checksum() != checksum {
+            return Err(anyhow!("Invalid address checksum: {text}"));
+        }
+
+        Ok(address)
+    }
+}
+
+/// Parsing the base58 address, the checksum, the prefix and the network byte are verified
+pub fn address_from_str(text: &str, network: Network) -> Result<Address> {
+    let address: Address = text.parse()?;
+    check_address(&address, network)?;
+
+    Ok(address)
+}
+
+/// Checking that the address has the prefix and belongs to the network
+pub fn check_address(address: &Address, network: Network) -> Result<()> {
+    if address[0] != ADDRESS_PREFIX {
+        return Err(anyhow!("Invalid address prefix: {}", address[0]));
+    }
+
+    let address_network = Network::try_from(address[1])?;
+    if address_network != network {
+        return Err(anyhow!(
+            "Address of {address_network:?} can not be used on {network:?}"
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{account::Account, wallet};
+
+    #[test]
+    fn address_checksum_and_network() {
+        let (_, public_key) = wallet::generate();
+        let address = Account::from_public_key(public_key, Network::Testnet).address;
+
+        let text = address.to_string();
+        assert_eq!(text.parse::<Address>().unwrap(), address);
+        assert_eq!(address_from_str(&text, Network::Testnet).unwrap(), address);
+        assert!(address_from_str(&text, Network::Mainnet).is_err());
+
+        // Raw addresses without the checksum are rejected
+        assert!(address_from_str(&address.to_base58(), Network::Testnet).is_err());
+
+        let mut bytes = text.from_base58().unwrap();
+        bytes[10] ^= 1;
+        assert!(address_from_str(&bytes.to_base58(), Network::Testnet).is_err());
+
+        let mut address = address;
+        address[0] = 0;
+        assert!(check_address(&address, Network::Testnet).is_err());
+    }
+}
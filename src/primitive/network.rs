@@ -1,7 +1,20 @@
+use anyhow::{anyhow, Error};
 use clap::ValueEnum;
 
-#[derive(Clone, Copy, Debug, ValueEnum)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
 pub enum Network {
     Testnet = 0,
     Mainnet = 1,
 }
+
+impl TryFrom<u8> for Network {
+    type Error = Error;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        match byte {
+            0 => Ok(Self::Testnet),
+            1 => Ok(Self::Mainnet),
+            _ => Err(anyhow!("Unknown network: {byte}")),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{state::State, transaction::Data};
+use crate::{constants::*, state::State, transaction::Data};
 use anyhow::Result;
 
 pub trait Validation {
@@ -10,8 +10,8 @@ pub trait Validation {
 
     fn minimum_fee(&self, data: &Data) -> u64 {
         match data {
-            Data::RotatePublicKey { .. } => 100000,
-            Data::Transfer { .. } => 100000,
+            Data::RotatePublicKey { .. } => MIN_FEE,
+            Data::Transfer { .. } => MIN_FEE,
         }
     }
 }
@@ -0,0 +1,25 @@
+use jsonrpc_http_server::{
+    hyper::{header::AUTHORIZATION, Body, Request, StatusCode},
+    RequestMiddlewareAction, Response,
+};
+
+/// Request middleware rejecting requests without the `Authorization: Bearer <token>` header
+pub fn authorization(token: String) -> impl Fn(Request<Body>) -> RequestMiddlewareAction {
+    let expected = format!("Bearer {token}");
+
+    move |request: Request<Body>| {
+        let authorized = request
+            .headers()
+            .get(AUTHORIZATION)
+            .map(|value| value.as_bytes())
+            == Some(expected.as_bytes());
+
+        if authorized {
+            request.into()
+        } else {
+            let mut response = Response::bad_request("RPC token is missing or invalid");
+            response.code = StatusCode::UNAUTHORIZED;
+            response.into()
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use super::response::AccountResponse;
+use anyhow::{anyhow, Result};
+use jsonrpc_core::{
+    serde_json::{self, json, Value},
+    Output, Response,
+};
+use serde::de::DeserializeOwned;
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+/// Minimal blocking JSON-RPC client of the node
+pub struct RpcClient {
+    address: String,
+    token: Option<String>,
+}
+
+impl RpcClient {
+    pub fn new(address: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            token: None,
+        }
+    }
+
+    /// Sending the token of a node started with an RPC token
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    pub fn get_account(&self, address: &str) -> Result<AccountResponse> {
+        self.call("gem_getAccount", json!([address]))
+    }
+
+    pub fn watch_address(&self, address: &str, label: &str) -> Result<bool> {
+        self.call("gem_watchAddress", json!([address, label]))
+    }
+
+    /// Rebuilding the history of the watched addresses, returns the number of scanned blocks
+    pub fn rescan(&self, from_height: u64) -> Result<u64> {
+        self.call("gem_rescan", json!([from_height]))
+    }
+
+    pub fn get_watched(&self) -> Result<Value> {
+        self.call("gem_getWatched", json!([]))
+    }
+
+    /// Submitting the hex encoded signed transaction, returns the transaction hash
+    pub fn send_transaction(&self, transaction: &str) -> Result<String> {
+        self.call("gem_sendTransaction", json!([transaction]))
+    }
+
+    pub fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        })
+        .to_string();
+
+        let mut stream = TcpStream::connect(&self.address)
+            .map_err(|error| anyhow!("Failed to connect to the node {}: {error}", self.address))?;
+        stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+
+        let authorization = match &self.token {
+            Some(token) => format!("Authorization: Bearer {token}\r\n"),
+            None => String::new(),
+        };
+
+        // HTTP/1.0 keeps the response body unchunked and closes the connection after it
+        write!(
+            stream,
+            "POST / HTTP/1.0\r\nHost: {}\r\n{authorization}Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            self.address,
+            body.len()
+        )?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| anyhow!("Invalid HTTP response of the node"))?;
+
+        let status = head.lines().next().unwrap_or_default();
+        if status.split_whitespace().nth(1) == Some("401") {
+            return Err(anyhow!("The node requires a valid RPC token"));
+        }
+
+        match serde_json::from_str::<Response>(body)? {
+            Response::Single(Output::Success(success)) => {
+                Ok(serde_json::from_value(success.result)?)
+            }
+            Response::Single(Output::Failure(failure)) => {
+                Err(anyhow!("RPC {method} failed: {}", failure.error))
+            }
+            Response::Batch(_) => Err(anyhow!("Unexpected batch response of the node")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpc_core::{Error, IoHandler};
+    use jsonrpc_http_server::ServerBuilder;
+
+    #[test]
+    fn call_http_server() {
+        let mut io = IoHandler::default();
+        io.add_sync_method("gem_getAccount", |_| {
+            Ok(json!({
+                "address": "address",
+                "public_key": "public_key",
+                "balance": 10,
+                "sequence_number": 3,
+            }))
+        });
+        io.add_sync_method("gem_sendTransaction", |_| Err(Error::invalid_request()));
+
+        let server = ServerBuilder::new(io)
+            .threads(1)
+            .start_http(&"127.0.0.1:0".parse().unwrap())
+            .unwrap();
+        let client = RpcClient::new(&server.address().to_string());
+
+        assert_eq!(client.get_account("address").unwrap().sequence_number, 3);
+        assert!(client.send_transaction("00").is_err());
+
+        server.close();
+    }
+
+    #[test]
+    fn call_with_token() {
+        let mut io = IoHandler::default();
+        io.add_sync_method("gem_stop", |_| Ok(json!(true)));
+
+        let server = ServerBuilder::new(io)
+            .threads(1)
+            .request_middleware(crate::rpc::auth::authorization("token".to_string()))
+            .start_http(&"127.0.0.1:0".parse().unwrap())
+            .unwrap();
+        let address = server.address().to_string();
+
+        let client = RpcClient::new(&address);
+        assert!(client.call::<bool>("gem_stop", json!([])).is_err());
+
+        let client = RpcClient::new(&address).with_token(Some("other".to_string()));
+        assert!(client.call::<bool>("gem_stop", json!([])).is_err());
+
+        let client = RpcClient::new(&address).with_token(Some("token".to_string()));
+        assert!(client.call::<bool>("gem_stop", json!([])).unwrap());
+
+        server.close();
+    }
+}
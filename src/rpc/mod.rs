@@ -1,14 +1,30 @@
-mod response;
+pub mod auth;
+pub mod client;
+pub mod response;
 
-use crate::{constants::*, state::State};
-use async_std::sync::{Arc, RwLock};
-use base58::FromBase58;
+use crate::{
+    constants::*,
+    primitive::*,
+    state::State,
+    swarm::limits::Metrics,
+    transaction::{Data, Transaction},
+    wallet::MiningKey,
+};
+use async_std::{
+    channel::Sender,
+    sync::{Arc, RwLock},
+    task,
+};
+use base58::{FromBase58, ToBase58};
 use jsonrpc_core::{
     types::error::{Error, ErrorCode},
     Result,
 };
 use jsonrpc_derive::rpc;
-use response::BlockResponse;
+use response::{
+    AccountProofResponse, AccountResponse, BlockResponse, MetricsResponse, WatchedResponse,
+};
+use std::time::Duration;
 
 #[rpc(server)]
 pub trait Rpc {
@@ -18,6 +34,26 @@ pub trait Rpc {
     fn get_block_by_hash(&self, hash: String) -> Result<BlockResponse>;
     #[rpc(name = "gem_getBlockByNumber")]
     fn get_block_by_number(&self, height: u64) -> Result<BlockResponse>;
+    #[rpc(name = "gem_getAccount")]
+    fn get_account(&self, address: String) -> Result<AccountResponse>;
+    #[rpc(name = "gem_sendTransaction")]
+    fn send_transaction(&self, transaction: String) -> Result<String>;
+    #[rpc(name = "gem_watchAddress")]
+    fn watch_address(&self, address: String, label: String) -> Result<bool>;
+    #[rpc(name = "gem_rescan")]
+    fn rescan(&self, from_height: u64) -> Result<u64>;
+    #[rpc(name = "gem_getWatched")]
+    fn get_watched(&self) -> Result<Vec<WatchedResponse>>;
+    #[rpc(name = "gem_getAccountProof")]
+    fn get_account_proof(&self, address: String) -> Result<AccountProofResponse>;
+    #[rpc(name = "gem_getNetworkMetrics")]
+    fn get_network_metrics(&self) -> Result<MetricsResponse>;
+    #[rpc(name = "gem_unlockWallet")]
+    fn unlock_wallet(&self, password: String, timeout: u64) -> Result<String>;
+    #[rpc(name = "gem_lockWallet")]
+    fn lock_wallet(&self) -> Result<bool>;
+    #[rpc(name = "gem_stop")]
+    fn stop(&self) -> Result<bool>;
 }
 
 pub enum RpcError {
@@ -25,6 +61,10 @@ pub enum RpcError {
     FromBase58,
     GetDatabase,
     HashCalculate,
+    Wallet,
+    InvalidTransaction,
+    Watch,
+    InvalidAddress,
 }
 
 impl RpcError {
@@ -34,17 +74,46 @@ impl RpcError {
             RpcError::FromBase58 => Error::new(ErrorCode::ServerError(1)),
             RpcError::GetDatabase => Error::new(ErrorCode::ServerError(2)),
             RpcError::HashCalculate => Error::new(ErrorCode::ServerError(3)),
+            RpcError::Wallet => Error::new(ErrorCode::ServerError(4)),
+            RpcError::InvalidTransaction => Error::new(ErrorCode::ServerError(5)),
+            RpcError::Watch => Error::new(ErrorCode::ServerError(6)),
+            RpcError::InvalidAddress => Error::new(ErrorCode::ServerError(7)),
         }
     }
 }
 
+/// Parsing the address with the checksum of the node network
+fn parse_address(address: &str, network: Network) -> Result<Address> {
+    address_from_str(address, network).map_err(|error| {
+        let mut rpc_error = RpcError::InvalidAddress.to_error();
+        rpc_error.message = error.to_string();
+        rpc_error
+    })
+}
+
 pub struct RpcHandler {
     state: Arc<RwLock<State>>,
+    metrics: Arc<Metrics>,
+    mining_key: Arc<RwLock<MiningKey>>,
+    transactions: Sender<Transaction>,
+    shutdown: Sender<()>,
 }
 
 impl RpcHandler {
-    pub fn new(state: Arc<RwLock<State>>) -> Self {
-        Self { state }
+    pub fn new(
+        state: Arc<RwLock<State>>,
+        metrics: Arc<Metrics>,
+        mining_key: Arc<RwLock<MiningKey>>,
+        transactions: Sender<Transaction>,
+        shutdown: Sender<()>,
+    ) -> Self {
+        Self {
+            state,
+            metrics,
+            mining_key,
+            transactions,
+            shutdown,
+        }
     }
 }
 
@@ -55,11 +124,7 @@ impl Rpc for RpcHandler {
             .try_read()
             .ok_or_else(|| RpcError::StateRead.to_error())?;
 
-        let bytes = address
-            .from_base58()
-            .map_err(|_| RpcError::FromBase58.to_error())?;
-        let mut address = EMPTY_ADDRESS;
-        address.copy_from_slice(bytes.as_slice());
+        let address = parse_address(&address, state.network())?;
 
         let account = state
             .database
@@ -77,8 +142,7 @@ impl Rpc for RpcHandler {
         let bytes = hash
             .from_base58()
             .map_err(|_| RpcError::FromBase58.to_error())?;
-        let mut hash = EMPTY_HASH;
-        hash.copy_from_slice(bytes.as_slice());
+        let hash = Hash::try_from(bytes.as_slice()).map_err(|_| RpcError::FromBase58.to_error())?;
 
         let block = state
             .database
@@ -105,4 +169,191 @@ impl Rpc for RpcHandler {
 
         Ok(block_response)
     }
+
+    fn get_account(&self, address: String) -> Result<AccountResponse> {
+        let state = self
+            .state
+            .try_read()
+            .ok_or_else(|| RpcError::StateRead.to_error())?;
+
+        let address = parse_address(&address, state.network())?;
+
+        let account = state
+            .database
+            .get_account_from_address(address)
+            .map_err(|_| RpcError::GetDatabase.to_error())?;
+
+        let mut response = AccountResponse::from_account(&account);
+        response.height = state.last_header.height;
+        response.pending_transactions = state.pending_transactions(&address);
+
+        Ok(response)
+    }
+
+    fn send_transaction(&self, transaction: String) -> Result<String> {
+        let invalid = |message: String| {
+            let mut error = RpcError::InvalidTransaction.to_error();
+            error.message = message;
+            error
+        };
+
+        let bytes = hex::decode(transaction).map_err(|error| invalid(error.to_string()))?;
+        let transaction: Transaction =
+            bincode::deserialize(&bytes).map_err(|error| invalid(error.to_string()))?;
+        let hash = transaction
+            .hash()
+            .map_err(|_| RpcError::HashCalculate.to_error())?;
+
+        {
+            let state = self
+                .state
+                .try_read()
+                .ok_or_else(|| RpcError::StateRead.to_error())?;
+
+            // Expired transactions are not accepted to the mempool
+            transaction
+                .check_expiry(state.last_header.height + 1, state.last_header.timestamp)
+                .map_err(|error| invalid(error.to_string()))?;
+
+            // Transfers to an address of another network are refused by the node,
+            // the recipient is not checked by the consensus rules
+            if let Data::Transfer { recipient, .. } = &transaction.data {
+                check_address(recipient, state.network()).map_err(|error| {
+                    let mut rpc_error = RpcError::InvalidAddress.to_error();
+                    rpc_error.message = error.to_string();
+                    rpc_error
+                })?;
+            }
+
+            if !state.contains_transaction(&hash) {
+                transaction
+                    .is_valid(&state)
+                    .map_err(|error| invalid(error.to_string()))?;
+            }
+        }
+
+        // The main loop adds the transaction to the mempool and announces it to the peers
+        self.transactions
+            .try_send(transaction)
+            .map_err(|error| invalid(error.to_string()))?;
+
+        Ok(hash.to_base58())
+    }
+
+    fn watch_address(&self, address: String, label: String) -> Result<bool> {
+        // Waiting for the write lock, try_write fails while the miner holds the read lock
+        let mut state = task::block_on(self.state.write());
+        let address = parse_address(&address, state.network())?;
+
+        state.watch_address(address, &label).map_err(|error| {
+            log::warn!("Watching the address failed: {error}");
+            RpcError::Watch.to_error()
+        })?;
+
+        Ok(true)
+    }
+
+    fn rescan(&self, from_height: u64) -> Result<u64> {
+        let failed = |error: anyhow::Error| {
+            log::warn!("Rescan failed: {error}");
+            RpcError::Watch.to_error()
+        };
+
+        task::block_on(self.state.read())
+            .check_rescan(from_height)
+            .map_err(failed)?;
+
+        // The state is locked for one chunk of blocks at a time, new blocks are processed
+        // between the chunks and record their history themselves
+        let mut height = from_height;
+        loop {
+            let scanned = task::block_on(self.state.read())
+                .rescan_blocks(height, RESCAN_CHUNK_SIZE)
+                .map_err(failed)?;
+            if scanned == 0 {
+                return Ok(height - from_height);
+            }
+            height += scanned;
+        }
+    }
+
+    fn get_watched(&self) -> Result<Vec<WatchedResponse>> {
+        let state = self
+            .state
+            .try_read()
+            .ok_or_else(|| RpcError::StateRead.to_error())?;
+
+        let watched = state
+            .watched()
+            .map_err(|_| RpcError::GetDatabase.to_error())?;
+
+        watched
+            .iter()
+            .map(|watched| {
+                let history = state
+                    .history(watched.address)
+                    .map_err(|_| RpcError::GetDatabase.to_error())?;
+                Ok(WatchedResponse::from_watched(watched, &history))
+            })
+            .collect()
+    }
+
+    fn get_account_proof(&self, address: String) -> Result<AccountProofResponse> {
+        let state = self
+            .state
+            .try_read()
+            .ok_or_else(|| RpcError::StateRead.to_error())?;
+
+        let address = parse_address(&address, state.network())?;
+
+        let account = state.database.get_account_from_address(address).ok();
+        let proof = state
+            .state_tree()
+            .proof(state.last_header.state_root, &address)
+            .map_err(|_| RpcError::GetDatabase.to_error())?;
+
+        AccountProofResponse::new(&state.last_header, account.as_ref(), &proof)
+    }
+
+    fn get_network_metrics(&self) -> Result<MetricsResponse> {
+        Ok(MetricsResponse::from_metrics(&self.metrics))
+    }
+
+    fn unlock_wallet(&self, password: String, timeout: u64) -> Result<String> {
+        let mut mining_key = self
+            .mining_key
+            .try_write()
+            .ok_or_else(|| RpcError::Wallet.to_error())?;
+
+        // The zero timeout keeps the wallet unlocked until gem_lockWallet
+        let timeout = (timeout > 0).then(|| Duration::from_secs(timeout));
+        let public_key = mining_key.unlock(&password, timeout).map_err(|error| {
+            log::warn!("Wallet unlock failed: {error}");
+            RpcError::Wallet.to_error()
+        })?;
+
+        log::info!(
+            "Wallet is unlocked by RPC, miner public key: {}",
+            public_key.to_base58()
+        );
+        Ok(public_key.to_base58())
+    }
+
+    fn lock_wallet(&self) -> Result<bool> {
+        let mut mining_key = self
+            .mining_key
+            .try_write()
+            .ok_or_else(|| RpcError::Wallet.to_error())?;
+
+        mining_key.lock();
+        log::info!("Wallet is locked by RPC");
+        Ok(true)
+    }
+
+    fn stop(&self) -> Result<bool> {
+        log::info!("Shutdown is requested by RPC");
+
+        // The shutdown is already in progress if the channel is full
+        Ok(self.shutdown.try_send(()).is_ok())
+    }
 }
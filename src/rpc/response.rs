@@ -1,16 +1,25 @@
 use crate::{
-    block::Block,
+    account::Account,
+    block::{Block, Header},
+    constants::*,
     primitive::*,
     rpc::RpcError,
+    state::{
+        tree::StateTree,
+        watch::{HistoryEntry, HistoryEvent, WatchedAddress},
+    },
+    swarm::limits::Metrics,
     transaction::{Data, Transaction},
 };
 use base58::ToBase58;
 use jsonrpc_core::Result;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
 
 #[derive(Serialize, Deserialize)]
 pub struct BlockResponse {
     id: String,
+    chain_id: ChainId,
     pow_hash: String,
     height: u64,
     timestamp: u128,
@@ -19,6 +28,7 @@ pub struct BlockResponse {
     generator_public_key: String,
     reward: u64,
     root: String,
+    state_root: String,
     transactions_count: u64,
     n_bits: u32,
     nonce: u64,
@@ -41,14 +51,16 @@ impl BlockResponse {
 
         Ok(Self {
             id: id.to_base58(),
+            chain_id: block.header.chain_id,
             pow_hash: block.header.pow_hash.to_base58(),
             height: block.header.height,
             timestamp: block.header.timestamp,
             prev_block: block.header.prev_block.to_base58(),
-            generator: block.header.generator.to_base58(),
+            generator: block.header.generator.to_string(),
             generator_public_key: block.header.generator_public_key.to_base58(),
             reward: block.header.reward,
             root: block.header.root.to_base58(),
+            state_root: block.header.state_root.to_base58(),
             transactions_count: block.header.transactions_count,
             n_bits: block.header.n_bits,
             nonce: block.header.nonce,
@@ -61,11 +73,13 @@ impl BlockResponse {
 #[derive(Serialize, Deserialize)]
 pub struct TransactionResponse {
     id: String,
+    chain_id: ChainId,
     sender: String,
     sender_public_key: String,
     sequence_number: u64,
     fee: u64,
     timestamp: u128,
+    valid_until_height: u64,
     data: DataResponse,
     signature: String,
 }
@@ -80,11 +94,13 @@ impl TransactionResponse {
 
         Ok(Self {
             id: id.to_base58(),
-            sender: transaction.sender.to_base58(),
+            chain_id: transaction.chain_id,
+            sender: transaction.sender.to_string(),
             sender_public_key: transaction.sender_public_key.to_base58(),
             sequence_number: transaction.sequence_number,
             fee: transaction.fee,
             timestamp: transaction.timestamp,
+            valid_until_height: transaction.valid_until_height,
             data,
             signature: transaction.signature.to_base58(),
         })
@@ -114,10 +130,181 @@ impl DataResponse {
                 amount,
                 attachment,
             } => DataResponse::Transfer {
-                recipient: recipient.to_base58(),
+                recipient: recipient.to_string(),
                 amount: *amount,
                 attachment: attachment.to_string(),
             },
         }
     }
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct MetricsResponse {
+    throttled_requests: u64,
+    throttled_gossip: u64,
+    rejected_connections: u64,
+    dropped_requests: u64,
+}
+
+impl MetricsResponse {
+    pub fn from_metrics(metrics: &Metrics) -> Self {
+        Self {
+            throttled_requests: metrics.throttled_requests.load(Ordering::Relaxed),
+            throttled_gossip: metrics.throttled_gossip.load(Ordering::Relaxed),
+            rejected_connections: metrics.rejected_connections.load(Ordering::Relaxed),
+            dropped_requests: metrics.dropped_requests.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AccountResponse {
+    pub address: String,
+    pub public_key: String,
+    pub balance: u64,
+    pub sequence_number: u64,
+    /// Height of the last block of the node
+    #[serde(default)]
+    pub height: u64,
+    /// Transactions of the account waiting in the mempool
+    #[serde(default)]
+    pub pending_transactions: usize,
+}
+
+impl AccountResponse {
+    pub fn from_account(account: &Account) -> Self {
+        Self {
+            address: account.address.to_string(),
+            public_key: account.public_key.to_base58(),
+            balance: account.balance,
+            sequence_number: account.sequence_number(),
+            height: 0,
+            pending_transactions: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AccountProofResponse {
+    height: u64,
+    state_root: String,
+    account: Option<AccountResponse>,
+    leaf: String,
+    proof: Vec<String>,
+}
+
+impl AccountProofResponse {
+    pub fn new(header: &Header, account: Option<&Account>, proof: &[Hash]) -> Result<Self> {
+        // The empty leaf proves that the account does not exist
+        let leaf = match account {
+            Some(account) => {
+                StateTree::leaf_hash(account).map_err(|_| RpcError::HashCalculate.to_error())?
+            }
+            None => EMPTY_HASH,
+        };
+
+        Ok(Self {
+            height: header.height,
+            state_root: header.state_root.to_base58(),
+            account: account.map(AccountResponse::from_account),
+            leaf: leaf.to_base58(),
+            proof: proof.iter().map(|hash| hash.to_base58()).collect(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WatchedResponse {
+    address: String,
+    label: String,
+    balance: u64,
+    history: Vec<HistoryResponse>,
+}
+
+impl WatchedResponse {
+    pub fn from_watched(watched: &WatchedAddress, history: &[HistoryEntry]) -> Self {
+        Self {
+            address: watched.address.to_string(),
+            label: watched.label.clone(),
+            balance: watched.balance,
+            history: history.iter().map(HistoryResponse::from_entry).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HistoryResponse {
+    height: u64,
+    block: String,
+    event: HistoryEventResponse,
+}
+
+impl HistoryResponse {
+    pub fn from_entry(entry: &HistoryEntry) -> Self {
+        Self {
+            height: entry.height,
+            block: entry.block.to_base58(),
+            event: HistoryEventResponse::from_event(&entry.event),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum HistoryEventResponse {
+    Reward {
+        amount: u64,
+    },
+    Received {
+        transaction: String,
+        sender: String,
+        amount: u64,
+    },
+    Sent {
+        transaction: String,
+        recipient: String,
+        amount: u64,
+        fee: u64,
+    },
+    RotatePublicKey {
+        transaction: String,
+        public_key: String,
+        fee: u64,
+    },
+}
+
+impl HistoryEventResponse {
+    pub fn from_event(event: &HistoryEvent) -> Self {
+        match event {
+            HistoryEvent::Reward { amount } => Self::Reward { amount: *amount },
+            HistoryEvent::Received {
+                transaction,
+                sender,
+                amount,
+            } => Self::Received {
+                transaction: transaction.to_base58(),
+                sender: sender.to_string(),
+                amount: *amount,
+            },
+            HistoryEvent::Sent {
+                transaction,
+                recipient,
+                amount,
+                fee,
+            } => Self::Sent {
+                transaction: transaction.to_base58(),
+                recipient: recipient.to_string(),
+                amount: *amount,
+                fee: *fee,
+            },
+            HistoryEvent::RotatePublicKey {
+                transaction,
+                public_key,
+                fee,
+            } => Self::RotatePublicKey {
+                transaction: transaction.to_base58(),
+                public_key: public_key.to_base58(),
+                fee: *fee,
+            },
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use super::State;
+use crate::{block::Block, constants::*, primitive::*};
+use anyhow::{anyhow, Result};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write},
+};
+
+/// Exporting blocks in the height range to a file of length-prefixed bincode blocks.
+/// If the file already contains blocks, the export continues after the last complete block
+pub fn export_blocks(state: &State, from: u64, to: Option<u64>, path: &str) -> Result<()> {
+    let to = to.unwrap_or(state.last_header.height);
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|error| anyhow!("Failed to open {path}: {error}"))?;
+
+    // Resuming after the last complete block, a partially written block is overwritten
+    let (last_height, length) = scan(&mut file)?;
+    file.set_len(length)?;
+    file.seek(SeekFrom::End(0))?;
+
+    let from = match last_height {
+        Some(height) if height >= from => {
+            log::info!("Resuming the export after block {height}");
+            height + 1
+        }
+        // The file would miss the blocks between its last block and the range
+        Some(height) if height + 1 < from => {
+            return Err(anyhow!(
+                "{path} ends at block {height}, the export must continue from block {}",
+                height + 1
+            ))
+        }
+        _ => from,
+    };
+
+    let mut writer = BufWriter::new(file);
+
+    for height in from..=to {
+        let block = state
+            .database
+            .get_block_from_height(height)
+            .map_err(|error| anyhow!("Block {height} is not available: {error}"))?;
+
+        let bytes = bincode::serialize(&block)
+            .map_err(|error| anyhow!("Failed to serialize block: {error:?}"))?;
+
+        writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        writer.write_all(&bytes)?;
+
+        if height % PROGRESS_INTERVAL == 0 {
+            log::info!("Exported blocks: {height}/{to}");
+        }
+    }
+
+    writer.flush()?;
+    log::info!("Blocks are exported up to height {to}");
+
+    Ok(())
+}
+
+/// Importing blocks from a file created by `export_blocks`. Blocks are validated the same way as
+/// blocks received by synchronization, blocks that are already in the chain are skipped and
+/// the import fails on a block of another chain
+pub fn import_blocks(state: &mut State, path: &str) -> Result<()> {
+    let file = File::open(path).map_err(|error| anyhow!("Failed to open {path}: {error}"))?;
+    let mut reader = BufReader::new(file);
+
+    let mut imported = 0;
+
+    while let Some(block) = read_block(&mut reader)? {
+        let height = block.header.height;
+
+        if height <= state.last_header.height {
+            // Headers below an imported snapshot are not stored and cannot be compared
+            let stored = match state.database.get_block_header_from_height(height) {
+                Ok(header) => header,
+                Err(_) if height <= state.database.get_base_height()? => continue,
+                Err(error) => return Err(error),
+            };
+
+            if stored.hash()? != block.header.hash()? {
+                return Err(anyhow!(
+                    "Block {height} does not match the block in the chain, the file is from another chain"
+                ));
+            }
+            continue;
+        }
+
+        block
+            .is_valid(state)
+            .map_err(|error| anyhow!("Block {height} is not valid: {error}"))?;
+        state.put_block(&block)?;
+
+        imported += 1;
+        if height % PROGRESS_INTERVAL == 0 {
+            log::info!("Imported blocks: {height}");
+        }
+    }
+
+    log::info!(
+        "Imported {imported} blocks, the last block: {}",
+        state.last_header.height
+    );
+
+    Ok(())
+}
+
+/// Reading the next block, `None` at the end of the file
+fn read_block(reader: &mut impl Read) -> Result<Option<Block>> {
+    let mut length = [0u8; 4];
+
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error.into()),
+    }
+
+    // The length is checked before allocating, a block never exceeds a network message
+    let length = u32::from_le_bytes(length) as usize;
+    if length > MAX_TRANSMIT_SIZE {
+        return Err(anyhow!(
+            "Block length {length} exceeds {MAX_TRANSMIT_SIZE} bytes"
+        ));
+    }
+
+    let mut bytes = vec![0u8; length];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|error| anyhow!("Failed to read block: {error}"))?;
+
+    let block = bincode::deserialize(&bytes)
+        .map_err(|error| anyhow!("Failed to deserialize block: {error:?}"))?;
+
+    Ok(Some(block))
+}
+
+/// Getting the height of the last complete block and the length of the file up to its end
+fn scan(file: &mut File) -> Result<(Option<u64>, u64)> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut reader = BufReader::new(file);
+
+    let mut last_height = None;
+    let mut length = 0;
+
+    // Reading stops at the end of the file or at a partially written block
+    while let Ok(Some(block)) = read_block(&mut reader) {
+        last_height = Some(block.header.height);
+        length = reader.stream_position()?;
+    }
+
+    Ok((last_height, length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{push_empty_blocks, Database};
+    use std::{env, fs};
+
+    #[test]
+    fn resume_export() {
+        let mut state =
+            State::from_database(Database::memory().unwrap(), Network::Testnet).unwrap();
+
+        push_empty_blocks(&mut state, EMPTY_PUBLIC_KEY, 1).unwrap();
+
+        let path = env::temp_dir().join(format!("gem-export-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        export_blocks(&state, 0, Some(0), path).unwrap();
+        let length = fs::metadata(path).unwrap().len();
+
+        // Block 1 would be missing from the file
+        assert!(export_blocks(&state, 2, None, path).is_err());
+
+        // A partially written block is overwritten on resume
+        let mut file = OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(&[1, 2, 3, 4, 5]).unwrap();
+        drop(file);
+
+        let mut file = File::open(path).unwrap();
+        assert_eq!(scan(&mut file).unwrap(), (Some(0), length));
+
+        export_blocks(&state, 0, None, path).unwrap();
+        let mut file = File::open(path).unwrap();
+        assert_eq!(scan(&mut file).unwrap().0, Some(1));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reject_another_chain() {
+        let mut state =
+            State::from_database(Database::memory().unwrap(), Network::Testnet).unwrap();
+        push_empty_blocks(&mut state, EMPTY_PUBLIC_KEY, 1).unwrap();
+
+        let mut fork = State::from_database(Database::memory().unwrap(), Network::Testnet).unwrap();
+        push_empty_blocks(&mut fork, [1; 32], 1).unwrap();
+
+        let path = env::temp_dir().join(format!("gem-fork-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        export_blocks(&state, 0, None, path).unwrap();
+        import_blocks(&mut state, path).unwrap();
+        assert!(import_blocks(&mut fork, path).is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reject_oversized_block() {
+        let bytes = u32::MAX.to_le_bytes();
+        assert!(read_block(&mut &bytes[..]).is_err());
+    }
+}
@@ -1,3 +1,8 @@
+use super::{
+    migration,
+    storage::{Batch, MemoryStorage, RocksDbConfig, RocksDbStorage, Storage},
+    watch::{HistoryEntry, WatchedAddress},
+};
 use crate::{
     account::Account,
     block::{Block, Header},
@@ -6,35 +11,142 @@ use crate::{
     transaction::{Transaction, Transactions},
 };
 use anyhow::{anyhow, Result};
-use rocksdb::{ColumnFamilyDescriptor, Options, WriteBatch, DB};
+use base58::ToBase58;
 
 pub struct Database {
-    db: DB,
+    storage: Box<dyn Storage>,
 }
 
 impl Database {
-    pub fn new(path: &str) -> Self {
-        let mut options = Options::default();
-        options.create_missing_column_families(true);
-        options.create_if_missing(true);
+    /// Opening the RocksDB database and upgrading its layout to the current version
+    pub fn new(path: &str) -> Result<Self> {
+        Self::open(path, &RocksDbConfig::default())
+    }
 
-        let db = DB::open_cf_descriptors(&options, path, Self::descriptors())
-            .unwrap_or_else(|error| panic!("Failed to open the database {path}: {error}"));
+    /// Opening the RocksDB database with the configuration
+    pub fn open(path: &str, config: &RocksDbConfig) -> Result<Self> {
+        Self::from_storage(Box::new(RocksDbStorage::open(path, config)?))
+    }
 
-        Self { db }
+    /// Creating an empty in-memory database
+    pub fn memory() -> Result<Self> {
+        Self::from_storage(Box::<MemoryStorage>::default())
     }
 
-    pub fn create_batch(&self) -> WriteBatch {
-        WriteBatch::default()
+    /// Opening the database over the storage backend
+    pub fn from_storage(storage: Box<dyn Storage>) -> Result<Self> {
+        let database = Self { storage };
+        migration::migrate(&database)?;
+
+        Ok(database)
+    }
+
+    pub fn get_version(&self) -> Result<Option<u32>> {
+        if !self.contains(INFO, b"version")? {
+            return Ok(None);
+        }
+
+        let bytes = self
+            .get(INFO, b"version")?
+            .try_into()
+            .map_err(|_| anyhow!("Invalid database version record"))?;
+
+        Ok(Some(u32::from_le_bytes(bytes)))
+    }
+
+    pub fn put_version(&self, version: u32) -> Result<()> {
+        let mut batch = self.create_batch();
+        self.put_batch(&mut batch, INFO, b"version", &version.to_le_bytes())?;
+
+        self.write(batch)
+    }
+
+    /// Getting the height of the imported snapshot or the last pruned block,
+    /// block transactions are stored starting from the next height
+    pub fn get_base_height(&self) -> Result<u64> {
+        if !self.contains(INFO, b"base_height")? {
+            return Ok(0);
+        }
+
+        let bytes = self
+            .get(INFO, b"base_height")?
+            .try_into()
+            .map_err(|_| anyhow!("Invalid base height record"))?;
+
+        Ok(u64::from_le_bytes(bytes))
     }
 
-    pub fn write(&self, batch: WriteBatch) -> Result<()> {
-        self.db
-            .write(batch)
-            .map_err(|error| anyhow!("Failed to write to the database: {error:?}"))
+    pub fn put_base_height(&self, batch: &mut Batch, height: u64) -> Result<()> {
+        self.put_batch(batch, INFO, b"base_height", &height.to_le_bytes())
     }
 
-    pub fn put_block_header(&self, batch: &mut WriteBatch, header: &Header) -> Result<()> {
+    /// Getting the hash of the last block of an interrupted reindexing
+    pub fn get_reindex_header(&self) -> Result<Option<Hash>> {
+        if !self.contains(INFO, b"reindex_header")? {
+            return Ok(None);
+        }
+
+        let bytes = self
+            .get(INFO, b"reindex_header")?
+            .try_into()
+            .map_err(|_| anyhow!("Invalid reindex header record"))?;
+
+        Ok(Some(bytes))
+    }
+
+    pub fn put_reindex_header(&self, hash: Option<Hash>) -> Result<()> {
+        let mut batch = self.create_batch();
+        match hash {
+            Some(hash) => self.put_batch(&mut batch, INFO, b"reindex_header", &hash)?,
+            None => self.delete_batch(&mut batch, INFO, b"reindex_header")?,
+        }
+
+        self.write(batch)
+    }
+
+    /// Persisting the written data before closing
+    pub fn flush(&self) -> Result<()> {
+        self.storage.flush()
+    }
+
+    /// Checking if blocks were written since the last clean shutdown
+    pub fn is_dirty(&self) -> Result<bool> {
+        self.contains(INFO, b"dirty")
+    }
+
+    /// Marking the database as written until it is closed
+    pub fn put_dirty(&self, batch: &mut Batch) -> Result<()> {
+        self.put_batch(batch, INFO, b"dirty", &[1])
+    }
+
+    /// Clearing the dirty mark and persisting the written data on a clean shutdown
+    pub fn close(&self) -> Result<()> {
+        let mut batch = self.create_batch();
+        self.delete_batch(&mut batch, INFO, b"dirty")?;
+        self.write(batch)?;
+
+        self.flush()
+    }
+
+    /// Deleting all keys of the column family
+    pub fn clear(&self, cf: &str) -> Result<()> {
+        self.storage.clear(cf)
+    }
+
+    /// Checking if the database does not contain any block
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(!self.contains(INFO, b"last_header")?)
+    }
+
+    pub fn create_batch(&self) -> Batch {
+        Batch::default()
+    }
+
+    pub fn write(&self, batch: Batch) -> Result<()> {
+        self.storage.write(batch)
+    }
+
+    pub fn put_block_header(&self, batch: &mut Batch, header: &Header) -> Result<()> {
         let value = bincode::serialize(&header)
             .map_err(|error| anyhow!("Failed to serialize header: {error:?}"))?;
 
@@ -94,12 +206,17 @@ impl Database {
         self.get_block_header_from_hash(hash)
     }
 
+    /// Storing the transaction hashes of the block along with the transaction bodies
     pub fn put_block_transactions(
         &self,
-        batch: &mut WriteBatch,
+        batch: &mut Batch,
         hash: Hash,
         transactions: &Transactions,
     ) -> Result<()> {
+        for transaction in &transactions.0 {
+            self.put_transaction(batch, transaction)?;
+        }
+
         self.put_batch(
             batch,
             BLOCK_TRANSACTIONS,
@@ -110,6 +227,19 @@ impl Database {
         Ok(())
     }
 
+    /// Deleting the transactions of a block, the transaction hashes are kept with an empty
+    /// body so pruned transactions are still known to the chain
+    pub fn delete_block_transactions(&self, batch: &mut Batch, hash: Hash) -> Result<()> {
+        let bytes = self.get(BLOCK_TRANSACTIONS, &hash)?;
+
+        for transaction_hash in bytes.chunks(32) {
+            self.put_batch(batch, TRANSACTIONS, transaction_hash, &[])?;
+        }
+        self.delete_batch(batch, BLOCK_TRANSACTIONS, &hash)?;
+
+        Ok(())
+    }
+
     pub fn get_block_transactions(&self, hash: Hash) -> Result<Transactions> {
         let bytes = self.get(BLOCK_TRANSACTIONS, &hash)?;
 
@@ -125,6 +255,75 @@ impl Database {
         Ok(transactions)
     }
 
+    /// Getting the transaction hashes of all stored blocks, including the blocks off the
+    /// main chain
+    pub fn get_stored_block_transactions(&self) -> Result<Vec<(Hash, Vec<Hash>)>> {
+        let mut blocks = vec![];
+        for (key, value) in self
+            .storage
+            .range(BLOCK_TRANSACTIONS, &EMPTY_HASH, &[u8::MAX; 33])?
+        {
+            let hash = key
+                .try_into()
+                .map_err(|_| anyhow!("Invalid block transactions key"))?;
+            let transactions = value
+                .chunks_exact(32)
+                .map(|chunk| {
+                    let mut hash = EMPTY_HASH;
+                    hash.copy_from_slice(chunk);
+                    hash
+                })
+                .collect();
+
+            blocks.push((hash, transactions));
+        }
+
+        Ok(blocks)
+    }
+
+    /// Deleting the transaction hashes of a block off the main chain and the listed
+    /// transaction bodies
+    pub fn delete_fork_transactions(
+        &self,
+        batch: &mut Batch,
+        hash: Hash,
+        transactions: &[Hash],
+    ) -> Result<()> {
+        for transaction_hash in transactions {
+            self.delete_batch(batch, TRANSACTIONS, transaction_hash)?;
+        }
+
+        self.delete_batch(batch, BLOCK_TRANSACTIONS, &hash)
+    }
+
+    /// Getting the hashes of main chain blocks whose transaction bodies are missing
+    pub fn find_incomplete_blocks(&self, last_height: u64) -> Result<Vec<Hash>> {
+        let mut incomplete = vec![];
+
+        // The genesis block is empty, snapshot blocks and pruned blocks have no transactions
+        for height in self.get_base_height()? + 1..=last_height {
+            let hash = self.get_block_header_from_height(height)?.hash()?;
+
+            // A block without the list of its transaction hashes is incomplete as well
+            let bytes = match self.storage.get(BLOCK_TRANSACTIONS, &hash)? {
+                Some(bytes) => bytes,
+                None => {
+                    incomplete.push(hash);
+                    continue;
+                }
+            };
+
+            for transaction_hash in bytes.chunks(32) {
+                if !self.contains(TRANSACTIONS, transaction_hash)? {
+                    incomplete.push(hash);
+                    break;
+                }
+            }
+        }
+
+        Ok(incomplete)
+    }
+
     pub fn get_block_from_hash(&self, hash: Hash) -> Result<Block> {
         let header = self.get_block_header_from_hash(hash)?;
         let transactions = self.get_block_transactions(header.hash()?)?;
@@ -145,7 +344,7 @@ impl Database {
         })
     }
 
-    pub fn put_transaction(&self, batch: &mut WriteBatch, transaction: &Transaction) -> Result<()> {
+    pub fn put_transaction(&self, batch: &mut Batch, transaction: &Transaction) -> Result<()> {
         let value = bincode::serialize(&transaction)
             .map_err(|error| anyhow!("Failed to serialize transaction: {error:?}"))?;
 
@@ -156,13 +355,22 @@ impl Database {
 
     pub fn get_transaction(&self, hash: Hash) -> Result<Transaction> {
         let bytes = self.get(TRANSACTIONS, &hash)?;
+        if bytes.is_empty() {
+            return Err(anyhow!("Transaction {} is pruned", hash.to_base58()));
+        }
+
         let transaction: Transaction = bincode::deserialize(&bytes[..])
             .map_err(|error| anyhow!("Failed to deserialize transaction: {error:?}"))?;
 
         Ok(transaction)
     }
 
-    pub fn put_account(&self, batch: &mut WriteBatch, account: &Account) -> Result<()> {
+    /// The transaction is in the blockchain, including pruned transactions
+    pub fn contains_transaction(&self, hash: Hash) -> Result<bool> {
+        self.contains(TRANSACTIONS, &hash)
+    }
+
+    pub fn put_account(&self, batch: &mut Batch, account: &Account) -> Result<()> {
         let value = bincode::serialize(&account)
             .map_err(|error| anyhow!("Failed to serialize account: {error:?}"))?;
 
@@ -170,7 +378,7 @@ impl Database {
         self.put_batch(
             batch,
             ACCOUNTS_PUBLIC_KEY,
-            &account.address,
+            account.address.as_ref(),
             &account.public_key,
         )?;
 
@@ -186,7 +394,7 @@ impl Database {
     }
 
     pub fn get_account_from_address(&self, address: Address) -> Result<Account> {
-        let bytes = self.get(ACCOUNTS_PUBLIC_KEY, &address)?;
+        let bytes = self.get(ACCOUNTS_PUBLIC_KEY, address.as_ref())?;
 
         let mut public_key = EMPTY_PUBLIC_KEY;
         public_key.copy_from_slice(bytes.as_slice());
@@ -196,7 +404,7 @@ impl Database {
 
     pub fn put_account_transactions(
         &self,
-        batch: &mut WriteBatch,
+        batch: &mut Batch,
         public_key: PublicKey,
         transactions: &Transactions,
     ) -> Result<()> {
@@ -215,7 +423,12 @@ impl Database {
 
         let mut transactions = Transactions::default();
 
+        // Pruned transactions are left out of the account history
         for bytes in self.get_multi(TRANSACTIONS, bytes.chunks(32).collect())? {
+            if bytes.is_empty() {
+                continue;
+            }
+
             let transaction: Transaction = bincode::deserialize(&bytes[..])
                 .map_err(|error| anyhow!("Failed to deserialize transaction: {error:?}"))?;
 
@@ -225,63 +438,174 @@ impl Database {
         Ok(transactions)
     }
 
-    fn descriptors() -> Vec<ColumnFamilyDescriptor> {
-        let options = Options::default();
+    pub fn put_tree_node(
+        &self,
+        batch: &mut Batch,
+        hash: Hash,
+        left: Hash,
+        right: Hash,
+    ) -> Result<()> {
+        self.put_batch(batch, STATE_TREE, &hash, &[left, right].concat())?;
 
-        vec![
-            ColumnFamilyDescriptor::new(BLOCK_HEADERS, options.clone()),
-            ColumnFamilyDescriptor::new(BLOCK_HEADERS_HASH, options.clone()),
-            ColumnFamilyDescriptor::new(BLOCK_TRANSACTIONS, options.clone()),
-            ColumnFamilyDescriptor::new(TRANSACTIONS, options.clone()),
-            ColumnFamilyDescriptor::new(ACCOUNTS, options.clone()),
-            ColumnFamilyDescriptor::new(ACCOUNTS_PUBLIC_KEY, options.clone()),
-            ColumnFamilyDescriptor::new(INFO, options),
-        ]
+        Ok(())
+    }
+
+    pub fn delete_tree_node(&self, batch: &mut Batch, hash: Hash) -> Result<()> {
+        self.delete_batch(batch, STATE_TREE, &hash)
+    }
+
+    pub fn get_tree_node(&self, hash: Hash) -> Result<(Hash, Hash)> {
+        let bytes = self.get(STATE_TREE, &hash)?;
+        if bytes.len() != 64 {
+            return Err(anyhow!("Invalid state tree node: {:?}", hash));
+        }
+
+        let mut left = EMPTY_HASH;
+        let mut right = EMPTY_HASH;
+        left.copy_from_slice(&bytes[..32]);
+        right.copy_from_slice(&bytes[32..]);
+
+        Ok((left, right))
     }
 
-    fn put_batch(&self, batch: &mut WriteBatch, cf: &str, key: &[u8], value: &[u8]) -> Result<()> {
-        let cf = self
-            .db
-            .cf_handle(cf)
-            .ok_or_else(|| anyhow!("Failed column family handle"))?;
+    pub fn put_watched(&self, batch: &mut Batch, watched: &WatchedAddress) -> Result<()> {
+        let value = bincode::serialize(watched)
+            .map_err(|error| anyhow!("Failed to serialize watched address: {error:?}"))?;
 
-        batch.put_cf(cf, key, value);
+        self.put_batch(batch, WATCHED, watched.address.as_ref(), &value)
+    }
+
+    pub fn get_watched(&self, address: Address) -> Result<WatchedAddress> {
+        let bytes = self.get(WATCHED, address.as_ref())?;
+        let watched: WatchedAddress = bincode::deserialize(&bytes[..])
+            .map_err(|error| anyhow!("Failed to deserialize watched address: {error:?}"))?;
+
+        Ok(watched)
+    }
+
+    /// Putting the history entries of the watched address in the block at the height,
+    /// each entry is stored under the address, the height and its index in the block
+    pub fn put_history(
+        &self,
+        batch: &mut Batch,
+        address: Address,
+        height: u64,
+        entries: &[HistoryEntry],
+    ) -> Result<()> {
+        for (index, entry) in entries.iter().enumerate() {
+            let value = bincode::serialize(entry)
+                .map_err(|error| anyhow!("Failed to serialize history entry: {error:?}"))?;
+
+            self.put_batch(
+                batch,
+                WATCHED_HISTORY,
+                &history_key(address, height, index as u32),
+                &value,
+            )?;
+        }
 
         Ok(())
     }
 
-    fn get(&self, cf: &str, key: &[u8]) -> Result<Vec<u8>> {
-        let cf = self
-            .db
-            .cf_handle(cf)
-            .ok_or_else(|| anyhow!("Failed column family handle"))?;
-
-        match self.db.get_cf(cf, key) {
-            Ok(Some(value)) => Ok(value),
-            Ok(None) => Err(anyhow!("Value not found")),
-            Err(error) => Err(anyhow!("Failed to reading data from the database: {error}")),
+    /// Getting the history entries of the watched address from the height
+    pub fn get_history(&self, address: Address, from_height: u64) -> Result<Vec<HistoryEntry>> {
+        self.storage
+            .range(
+                WATCHED_HISTORY,
+                &history_key(address, from_height, 0),
+                &history_key(address, u64::MAX, u32::MAX),
+            )?
+            .into_iter()
+            .map(|(_, value)| {
+                bincode::deserialize(&value)
+                    .map_err(|error| anyhow!("Failed to deserialize history entry: {error:?}"))
+            })
+            .collect()
+    }
+
+    /// Deleting the history entries of the watched address in the height range,
+    /// `to_height` exclusive
+    pub fn delete_history(
+        &self,
+        batch: &mut Batch,
+        address: Address,
+        from_height: u64,
+        to_height: u64,
+    ) -> Result<()> {
+        for (key, _) in self.storage.range(
+            WATCHED_HISTORY,
+            &history_key(address, from_height, 0),
+            &history_key(address, to_height, 0),
+        )? {
+            self.delete_batch(batch, WATCHED_HISTORY, &key)?;
         }
+
+        Ok(())
     }
 
-    fn get_multi(&self, cf: &str, keys: Vec<&[u8]>) -> Result<Vec<Vec<u8>>> {
-        let cf = self
-            .db
-            .cf_handle(cf)
-            .ok_or_else(|| anyhow!("Failed column family handle"))?;
-        let keys = keys.into_iter().map(|key| (&cf, key)).collect::<Vec<_>>();
-
-        let mut result = vec![];
-
-        for item in self.db.multi_get_cf(keys) {
-            match item {
-                Ok(Some(value)) => result.push(value),
-                Ok(None) => return Err(anyhow!("Value not found")),
-                Err(error) => {
-                    return Err(anyhow!("Failed to reading data from the database: {error}"))
-                }
-            }
+    /// Getting the list of the watched addresses
+    pub fn get_watched_addresses(&self) -> Result<Vec<Address>> {
+        if !self.contains(INFO, b"watched")? {
+            return Ok(vec![]);
         }
 
-        Ok(result)
+        let bytes = self.get(INFO, b"watched")?;
+        Ok(bytes
+            .chunks_exact(32)
+            .map(|chunk| {
+                let mut address = EMPTY_ADDRESS;
+                address.copy_from_slice(chunk);
+                address
+            })
+            .collect())
+    }
+
+    pub fn put_watched_addresses(&self, batch: &mut Batch, addresses: &[Address]) -> Result<()> {
+        let bytes: Vec<u8> = addresses.iter().flat_map(|address| address.0).collect();
+        self.put_batch(batch, INFO, b"watched", &bytes)
+    }
+
+    fn put_batch(
+        &self,
+        batch: &mut Batch,
+        cf: &'static str,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<()> {
+        batch.put(cf, key, value);
+
+        Ok(())
+    }
+
+    fn delete_batch(&self, batch: &mut Batch, cf: &'static str, key: &[u8]) -> Result<()> {
+        batch.delete(cf, key);
+
+        Ok(())
+    }
+
+    fn get(&self, cf: &str, key: &[u8]) -> Result<Vec<u8>> {
+        self.storage
+            .get(cf, key)?
+            .ok_or_else(|| anyhow!("Value not found"))
+    }
+
+    fn contains(&self, cf: &str, key: &[u8]) -> Result<bool> {
+        Ok(self.storage.get(cf, key)?.is_some())
+    }
+
+    fn get_multi(&self, cf: &str, keys: Vec<&[u8]>) -> Result<Vec<Vec<u8>>> {
+        self.storage
+            .multi_get(cf, keys)?
+            .into_iter()
+            .map(|value| value.ok_or_else(|| anyhow!("Value not found")))
+            .collect()
     }
 }
+
+/// Key of the history entry, the big-endian height and index keep the entries in block order
+fn history_key(address: Address, height: u64, index: u32) -> Vec<u8> {
+    let mut key = address.to_vec();
+    key.extend_from_slice(&height.to_be_bytes());
+    key.extend_from_slice(&index.to_be_bytes());
+    key
+}
@@ -0,0 +1,120 @@
+use super::database::Database;
+use anyhow::{anyhow, Result};
+
+/// Current version of the database layout
+pub const DATABASE_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Step from a database layout version to the next one
+enum Migration {
+    /// The stored data is upgraded in place
+    Upgrade(fn(&Database) -> Result<()>),
+    /// The stored blocks cannot be converted, the version is only recorded for an empty
+    /// database and a database with blocks must be removed to synchronize again
+    Resync(&'static str),
+}
+
+/// Database layout migrations, the migration at index `n` upgrades version `n` to `n + 1`
+const MIGRATIONS: [Migration; 4] = [
+    Migration::Upgrade(unversioned),
+    // Changing the header hashes and signatures
+    Migration::Resync("block headers commit to the state root since version 2"),
+    // Changing the signatures of the headers and the transactions, the blocks stored by
+    // version 2 may also predate the commitment of the PoW hash to the mining target and
+    // the sequence number rule accepting only the next number of the sender
+    Migration::Resync(
+        "headers and transactions sign the chain id and PoW hashes commit to the mining target since version 3",
+    ),
+    // Changing the encoding and the signatures of the transactions
+    Migration::Resync("transactions sign their expiry height since version 4"),
+];
+
+/// Upgrading the database layout to the current version
+pub fn migrate(database: &Database) -> Result<()> {
+    let version = match database.get_version()? {
+        Some(version) => version,
+        // A new database is created with the current layout
+        None if database.is_empty()? => return database.put_version(DATABASE_VERSION),
+        None => 0,
+    };
+
+    if version > DATABASE_VERSION {
+        return Err(anyhow!(
+            "Database version {version} is newer than the supported version {DATABASE_VERSION}, please update the node"
+        ));
+    }
+
+    let pending = &MIGRATIONS[version as usize..];
+
+    // Nothing is upgraded if any of the steps requires the blocks to be synchronized again
+    if !database.is_empty()? {
+        let reasons: Vec<&str> = pending
+            .iter()
+            .filter_map(|migration| match migration {
+                Migration::Resync(reason) => Some(*reason),
+                Migration::Upgrade(_) => None,
+            })
+            .collect();
+
+        if !reasons.is_empty() {
+            return Err(anyhow!(
+                "Database version {version} cannot be upgraded to version {DATABASE_VERSION}: {}. Remove the data directory to synchronize again",
+                reasons.join(", ")
+            ));
+        }
+    }
+
+    for (from, migration) in (version..).zip(pending) {
+        let to = from + 1;
+
+        if let Migration::Upgrade(upgrade) = migration {
+            log::info!("Migrating the database from version {from} to {to}");
+            upgrade(database)?;
+        }
+
+        // The version is written after each step so an interrupted upgrade resumes from it
+        database.put_version(to)?;
+        log::info!("Database is migrated to version {to}");
+    }
+
+    Ok(())
+}
+
+/// Databases created before versioning have the same layout as version 1
+fn unversioned(_: &Database) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuse_newer_version() {
+        let database = Database::memory().unwrap();
+        assert_eq!(database.get_version().unwrap(), Some(DATABASE_VERSION));
+
+        database.put_version(DATABASE_VERSION + 1).unwrap();
+        assert!(migrate(&database).is_err());
+    }
+
+    #[test]
+    fn refuse_resync_with_blocks() {
+        let database = Database::memory().unwrap();
+
+        // An empty database only records the version
+        database.put_version(1).unwrap();
+        migrate(&database).unwrap();
+        assert_eq!(database.get_version().unwrap(), Some(DATABASE_VERSION));
+
+        let mut batch = database.create_batch();
+        database
+            .put_block_header(&mut batch, &crate::block::genesis::simple().header)
+            .unwrap();
+        database.write(batch).unwrap();
+
+        database.put_version(1).unwrap();
+        let error = migrate(&database).unwrap_err();
+        assert!(error.to_string().contains("Remove the data directory"));
+        assert_eq!(database.get_version().unwrap(), Some(1));
+    }
+}
@@ -1,8 +1,15 @@
+pub mod archive;
 mod database;
+mod migration;
+mod reindex;
+pub mod snapshot;
+pub mod storage;
+pub mod tree;
+pub mod watch;
 
 use crate::{
     account::Account,
-    block::{genesis, Block, Header},
+    block::{compact::PartialBlock, genesis, Block, Header},
     constants::*,
     pow::{
         lwma::Lwma1,
@@ -13,24 +20,37 @@ use crate::{
 };
 use anyhow::{anyhow, Result};
 use base58::ToBase58;
-use database::Database;
-use std::{collections::HashMap, str::FromStr};
+pub use database::Database;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+use storage::Batch;
+use tree::StateTree;
 
 pub struct State {
     pub database: Database,
     mempool: Vec<Transaction>,
+    partial_blocks: HashMap<Hash, PartialBlock>,
+    requested_blocks: HashSet<Hash>,
     randomx: RandomXFactory,
     pub lwma1: Lwma1,
     pub last_header: Header,
     network: Network,
+    chain_id: ChainId,
+    dirty: bool,
+    prune_depth: Option<u64>,
     pub is_sync: bool,
 }
 
 impl State {
     /// Blockchain state initialization
     pub fn new(path: &str, network: Network) -> Result<Self> {
-        let database = Database::new(path);
+        Self::from_database(Database::new(path)?, network)
+    }
 
+    /// Blockchain state initialization over an opened database
+    pub fn from_database(database: Database, network: Network) -> Result<Self> {
         // Initializing RandomX
         let randomx = RandomXFactory::default();
 
@@ -68,13 +88,32 @@ impl State {
 
         let height = last_header.height;
 
+        // Checking that the transaction bodies of all stored blocks are present
+        // after the node was stopped without closing the database
+        if database.is_dirty()? {
+            log::warn!("Database was not closed cleanly, checking the block transactions");
+
+            let incomplete = database.find_incomplete_blocks(height)?;
+            for hash in incomplete.iter() {
+                log::error!(
+                    "Block transactions are missing in the database: {}",
+                    hash.to_base58()
+                );
+            }
+        }
+
         let mut state = State {
             database,
             mempool: vec![],
+            partial_blocks: HashMap::new(),
+            requested_blocks: HashSet::new(),
             randomx,
             lwma1,
             last_header,
             network,
+            chain_id: genesis::chain_id(network)?,
+            dirty: false,
+            prune_depth: None,
             is_sync: false,
         };
 
@@ -87,6 +126,127 @@ impl State {
     pub fn put_block(&mut self, block: &Block) -> Result<()> {
         let mut batch = self.database.create_batch();
 
+        let accounts = self.block_accounts(block)?;
+
+        for account in accounts.values() {
+            self.database.put_account(&mut batch, account)?;
+        }
+
+        // Recording the block in the history of the watched addresses
+        self.scan_watched(&mut batch, block, &accounts)?;
+
+        // Updating the state tree with the changed accounts
+        let state_root = self.update_state_tree(&mut batch, &accounts)?;
+        if state_root != block.header.state_root {
+            return Err(anyhow!(
+                "State root does not match: {}",
+                block.header.height
+            ));
+        }
+
+        // Adding block and block transactions to the database
+        self.database.put_block_header(&mut batch, &block.header)?;
+        self.database.put_block_transactions(
+            &mut batch,
+            block.header.hash()?,
+            &block.transactions,
+        )?;
+
+        // Deleting the transactions of blocks deeper than the pruning depth
+        if let Some(depth) = self.prune_depth {
+            self.prune_blocks(&mut batch, block.header.height.saturating_sub(depth))?;
+        }
+
+        // The database is marked until it is closed with the first block written
+        if !self.dirty {
+            self.database.put_dirty(&mut batch)?;
+        }
+
+        // Writing to the database
+        self.database.write(batch)?;
+        self.dirty = true;
+        // Update the last block
+        self.last_header = block.header.clone();
+
+        // Last sequence numbers of the senders taken by the block
+        let mut sequence_numbers: HashMap<Address, u64> = HashMap::new();
+        for transaction in &block.transactions.0 {
+            let number = sequence_numbers.entry(transaction.sender).or_default();
+            *number = std::cmp::max(*number, transaction.sequence_number);
+        }
+
+        // Evicting the included transactions and the transactions that can no longer be
+        // included in the next blocks
+        let count = self.mempool.len();
+        self.mempool.retain(|transaction| {
+            let used = sequence_numbers
+                .get(&transaction.sender)
+                .is_some_and(|number| transaction.sequence_number <= *number);
+
+            !used && !transaction.is_expired(block.header.height + 1, block.header.timestamp)
+        });
+        if self.mempool.len() < count {
+            log::trace!(
+                "Included and expired transactions are evicted from the mempool: {}",
+                count - self.mempool.len()
+            );
+        }
+
+        // Mining difficulty recalculation
+        self.lwma_calculate(self.last_header.height)?;
+
+        Ok(())
+    }
+
+    /// Closing the database on a clean shutdown, the block transactions are not checked
+    /// on the next start
+    pub fn close(&mut self) -> Result<()> {
+        self.database.close()?;
+        self.dirty = false;
+
+        Ok(())
+    }
+
+    /// Enabling the deletion of block transactions deeper than the depth,
+    /// headers and accounts are retained
+    pub fn set_prune_depth(&mut self, depth: u64) -> Result<()> {
+        if depth < MIN_PRUNE_DEPTH {
+            return Err(anyhow!(
+                "Pruning depth must be at least {MIN_PRUNE_DEPTH} blocks"
+            ));
+        }
+
+        self.prune_depth = Some(depth);
+
+        Ok(())
+    }
+
+    /// Calculating the state root after applying the block
+    pub fn calculate_state_root(&self, block: &Block) -> Result<Hash> {
+        let accounts = self.block_accounts(block)?;
+
+        // Nodes of the state tree are discarded
+        let mut batch = self.database.create_batch();
+        self.update_state_tree(&mut batch, &accounts)
+    }
+
+    /// Getting the network of the blockchain
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Getting the chain id signed by the headers and the transactions of the network
+    pub fn chain_id(&self) -> ChainId {
+        self.chain_id
+    }
+
+    /// Getting the state tree at the last block
+    pub fn state_tree(&self) -> StateTree<'_> {
+        StateTree::new(&self.database)
+    }
+
+    /// Calculating the accounts changed by the block
+    fn block_accounts(&self, block: &Block) -> Result<HashMap<Address, Account>> {
         let mut accounts: HashMap<Address, Account> = HashMap::new();
         let fees = 0;
 
@@ -97,8 +257,6 @@ impl State {
         // * Fee check
         // Create or Update account (balance or pub key)
 
-        // TODO: Apply Diff to DB
-
         // Аccrue a reward to the miner
         let mut generator = if let Some(account) = accounts.get(&block.header.generator) {
             account.clone()
@@ -116,31 +274,45 @@ impl State {
 
         accounts.insert(generator.address, generator);
 
-        for account in accounts.values() {
-            self.database.put_account(&mut batch, account)?;
-        }
+        Ok(accounts)
+    }
 
-        // Adding block and block transactions to the database
-        self.database.put_block_header(&mut batch, &block.header)?;
-        self.database.put_block_transactions(
-            &mut batch,
-            block.header.hash()?,
-            &block.transactions,
-        )?;
+    fn prune_blocks(&self, batch: &mut Batch, height: u64) -> Result<()> {
+        let base_height = self.database.get_base_height()?;
+        if height <= base_height {
+            return Ok(());
+        }
 
-        // Writing to the database
-        self.database.write(batch)?;
-        // Update the last block
-        self.last_header = block.header.clone();
+        for height in base_height + 1..=height {
+            let hash = self.database.get_block_header_from_height(height)?.hash()?;
+            self.database.delete_block_transactions(batch, hash)?;
+        }
+        self.database.put_base_height(batch, height)?;
 
-        // Mining difficulty recalculation
-        self.lwma_calculate(self.last_header.height)?;
+        log::trace!("Block transactions are pruned up to height {height}");
 
         Ok(())
     }
 
-    /// Put a transaction to the mempool of the blockchain
+    fn update_state_tree(
+        &self,
+        batch: &mut Batch,
+        accounts: &HashMap<Address, Account>,
+    ) -> Result<Hash> {
+        let mut leaves = vec![];
+        for account in accounts.values() {
+            leaves.push((account.address, StateTree::leaf_hash(account)?));
+        }
+
+        self.state_tree()
+            .update(batch, self.last_header.state_root, leaves)
+    }
+
+    /// Put a transaction to the mempool of the blockchain, it must be valid in the next block.
+    /// The time is the timestamp of the last block, so all nodes accept the same transactions
     pub fn put_transaction_mempool(&mut self, transaction: Transaction) -> Result<()> {
+        transaction.check_expiry(self.last_header.height + 1, self.last_header.timestamp)?;
+
         let id = transaction
             .hash()
             .map_err(|error| anyhow!("Failed to calculate hash transactions: {error:?}"))?
@@ -152,6 +324,70 @@ impl State {
         Ok(())
     }
 
+    /// Getting the transactions of the mempool
+    pub fn mempool(&self) -> &[Transaction] {
+        &self.mempool
+    }
+
+    /// Counting the mempool transactions of the sender
+    pub fn pending_transactions(&self, sender: &Address) -> usize {
+        self.mempool
+            .iter()
+            .filter(|transaction| &transaction.sender == sender)
+            .count()
+    }
+
+    /// Get a transaction from the mempool by hash
+    pub fn get_transaction_mempool(&self, hash: &Hash) -> Option<&Transaction> {
+        self.mempool
+            .iter()
+            .find(|transaction| transaction.hash().ok().as_ref() == Some(hash))
+    }
+
+    /// The transaction is in the mempool or in the blockchain, pruned transactions included
+    pub fn contains_transaction(&self, hash: &Hash) -> bool {
+        self.get_transaction_mempool(hash).is_some()
+            || self.database.contains_transaction(*hash).unwrap_or(false)
+    }
+
+    /// Put a block awaiting missing transactions
+    pub fn put_partial_block(&mut self, hash: Hash, block: PartialBlock) {
+        if self.partial_blocks.len() >= MAX_PARTIAL_BLOCKS {
+            // Evicting the block with the lowest height
+            if let Some(hash) = self
+                .partial_blocks
+                .iter()
+                .min_by_key(|(_, block)| block.header.height)
+                .map(|(hash, _)| *hash)
+            {
+                self.partial_blocks.remove(&hash);
+            }
+        }
+
+        self.partial_blocks.insert(hash, block);
+    }
+
+    /// Take a block awaiting missing transactions
+    pub fn take_partial_block(&mut self, hash: &Hash) -> Option<PartialBlock> {
+        self.partial_blocks.remove(hash)
+    }
+
+    /// Recording a full block requested from a peer
+    pub fn put_block_request(&mut self, hash: Hash) {
+        if self.requested_blocks.len() >= MAX_PARTIAL_BLOCKS {
+            if let Some(hash) = self.requested_blocks.iter().next().copied() {
+                self.requested_blocks.remove(&hash);
+            }
+        }
+
+        self.requested_blocks.insert(hash);
+    }
+
+    /// Take the request of a full block, false if the block was not requested
+    pub fn take_block_request(&mut self, hash: &Hash) -> bool {
+        self.requested_blocks.remove(hash)
+    }
+
     /// Creating a new RandomX instance from height
     pub fn create_randomx_vm_from_height(&self, height: u64) -> Result<RandomXVMInstance> {
         let height = height.div_euclid(RANDOMX_CHANGE_KEY) * RANDOMX_CHANGE_KEY;
@@ -167,3 +403,269 @@ impl State {
         self.lwma1.calculate(headers)
     }
 }
+
+/// Putting a block of the transactions mined by the generator on top of the last block
+#[cfg(test)]
+pub(crate) fn push_block(
+    state: &mut State,
+    generator: PublicKey,
+    transactions: Vec<Transaction>,
+) -> Result<Block> {
+    let height = state.last_header.height + 1;
+    let header = Header::new(
+        state.chain_id(),
+        height,
+        height as u128,
+        state.last_header.hash()?,
+        Account::from_public_key(generator, state.network()).address,
+        generator,
+        100,
+        EMPTY_HASH,
+        transactions.len() as u64,
+    );
+    let mut block = Block {
+        header,
+        transactions: Default::default(),
+    };
+    for transaction in transactions {
+        block.transactions.push(transaction);
+    }
+    block.header.state_root = state.calculate_state_root(&block)?;
+    state.put_block(&block)?;
+
+    Ok(block)
+}
+
+/// Putting empty blocks mined by the generator on top of the last block
+#[cfg(test)]
+pub(crate) fn push_empty_blocks(state: &mut State, generator: PublicKey, count: u64) -> Result<()> {
+    for _ in 0..count {
+        push_block(state, generator, vec![])?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn put_block_in_memory() {
+        let mut state =
+            State::from_database(Database::memory().unwrap(), Network::Testnet).unwrap();
+        assert_eq!(state.last_header.height, 0);
+
+        let header = Header::new(
+            state.chain_id(),
+            1,
+            1,
+            state.last_header.hash().unwrap(),
+            EMPTY_ADDRESS,
+            EMPTY_PUBLIC_KEY,
+            100,
+            EMPTY_HASH,
+            0,
+        );
+        let mut block = Block {
+            header,
+            transactions: Default::default(),
+        };
+        assert!(state.put_block(&block).is_err());
+
+        block.header.state_root = state.calculate_state_root(&block).unwrap();
+        state.put_block(&block).unwrap();
+
+        assert_eq!(state.last_header.height, 1);
+        assert_eq!(
+            state
+                .database
+                .get_last_block_header()
+                .unwrap()
+                .hash()
+                .unwrap(),
+            block.header.hash().unwrap()
+        );
+        assert_eq!(
+            state
+                .database
+                .get_account_from_public_key(EMPTY_PUBLIC_KEY)
+                .unwrap()
+                .balance,
+            100
+        );
+    }
+    #[test]
+    fn prune_block_transactions() {
+        let mut state =
+            State::from_database(Database::memory().unwrap(), Network::Testnet).unwrap();
+        assert!(state.set_prune_depth(MIN_PRUNE_DEPTH - 1).is_err());
+        state.set_prune_depth(MIN_PRUNE_DEPTH).unwrap();
+
+        let data = crate::transaction::Data::RotatePublicKey {
+            public_key: EMPTY_PUBLIC_KEY,
+        };
+        let transaction = Transaction::new(
+            state.chain_id(),
+            EMPTY_ADDRESS,
+            EMPTY_PUBLIC_KEY,
+            1,
+            MIN_FEE,
+            0,
+            TRANSACTION_LIFETIME_BLOCKS,
+            data,
+        );
+        let transaction_hash = transaction.hash().unwrap();
+
+        push_block(&mut state, EMPTY_PUBLIC_KEY, vec![transaction]).unwrap();
+        push_empty_blocks(&mut state, EMPTY_PUBLIC_KEY, MIN_PRUNE_DEPTH + 1).unwrap();
+
+        assert_eq!(state.database.get_base_height().unwrap(), 2);
+        assert!(state.database.get_block_from_height(2).is_err());
+        assert!(state.database.get_block_header_from_height(2).is_ok());
+        assert!(state.database.get_block_from_height(3).is_ok());
+
+        // The pruned transaction is still known, its body is not
+        assert!(state.contains_transaction(&transaction_hash));
+        let error = state
+            .database
+            .get_transaction(transaction_hash)
+            .unwrap_err();
+        assert!(error.to_string().contains("pruned"));
+    }
+
+    #[test]
+    fn detect_missing_transactions() {
+        let mut state =
+            State::from_database(Database::memory().unwrap(), Network::Testnet).unwrap();
+        assert!(!state.database.is_dirty().unwrap());
+
+        let data = crate::transaction::Data::RotatePublicKey {
+            public_key: EMPTY_PUBLIC_KEY,
+        };
+        let transaction = Transaction::new(
+            state.chain_id(),
+            EMPTY_ADDRESS,
+            EMPTY_PUBLIC_KEY,
+            1,
+            MIN_FEE,
+            0,
+            TRANSACTION_LIFETIME_BLOCKS,
+            data,
+        );
+        let transaction_hash = transaction.hash().unwrap();
+
+        let block = push_block(&mut state, EMPTY_PUBLIC_KEY, vec![transaction]).unwrap();
+
+        assert!(state.database.is_dirty().unwrap());
+        assert!(state.database.find_incomplete_blocks(1).unwrap().is_empty());
+
+        // The body of the block transaction is lost
+        let mut batch = state.database.create_batch();
+        batch.delete(TRANSACTIONS, &transaction_hash);
+        state.database.write(batch).unwrap();
+
+        assert_eq!(
+            state.database.find_incomplete_blocks(1).unwrap(),
+            vec![block.header.hash().unwrap()]
+        );
+
+        // The list of the block transaction hashes is lost
+        let mut batch = state.database.create_batch();
+        batch.delete(BLOCK_TRANSACTIONS, &block.header.hash().unwrap());
+        state.database.write(batch).unwrap();
+
+        assert_eq!(
+            state.database.find_incomplete_blocks(1).unwrap(),
+            vec![block.header.hash().unwrap()]
+        );
+
+        state.close().unwrap();
+        assert!(!state.database.is_dirty().unwrap());
+
+        let state = State::from_database(state.database, Network::Testnet).unwrap();
+        assert_eq!(state.last_header.height, 1);
+    }
+
+    #[test]
+    fn evict_expired_transactions() {
+        let mut state =
+            State::from_database(Database::memory().unwrap(), Network::Testnet).unwrap();
+
+        // The mempool checks the transactions against the last block, not the local clock
+        let data = crate::transaction::Data::RotatePublicKey {
+            public_key: EMPTY_PUBLIC_KEY,
+        };
+        let transaction = Transaction::new(
+            state.chain_id(),
+            EMPTY_ADDRESS,
+            EMPTY_PUBLIC_KEY,
+            1,
+            MIN_FEE,
+            state.last_header.timestamp,
+            1,
+            data,
+        );
+
+        let mut expired = transaction.clone();
+        expired.valid_until_height = 0;
+        assert!(state.put_transaction_mempool(expired).is_err());
+        let mut future = transaction.clone();
+        future.timestamp = state.last_header.timestamp + MAX_TRANSACTION_FUTURE + 1;
+        assert!(state.put_transaction_mempool(future).is_err());
+        state.put_transaction_mempool(transaction).unwrap();
+
+        push_empty_blocks(&mut state, EMPTY_PUBLIC_KEY, 1).unwrap();
+        assert!(state.mempool().is_empty());
+    }
+
+    #[test]
+    fn block_requests() {
+        let mut state =
+            State::from_database(Database::memory().unwrap(), Network::Testnet).unwrap();
+        assert!(!state.take_block_request(&EMPTY_HASH));
+
+        state.put_block_request(EMPTY_HASH);
+        assert!(state.take_block_request(&EMPTY_HASH));
+        assert!(!state.take_block_request(&EMPTY_HASH));
+
+        // The number of the outstanding requests is bounded
+        for index in 0..=MAX_PARTIAL_BLOCKS {
+            state.put_block_request([index as u8; 32]);
+        }
+        assert_eq!(state.requested_blocks.len(), MAX_PARTIAL_BLOCKS);
+    }
+
+    #[test]
+    fn evict_included_transactions() {
+        let mut state =
+            State::from_database(Database::memory().unwrap(), Network::Testnet).unwrap();
+
+        let data = crate::transaction::Data::RotatePublicKey {
+            public_key: EMPTY_PUBLIC_KEY,
+        };
+        let transaction = Transaction::new(
+            state.chain_id(),
+            EMPTY_ADDRESS,
+            EMPTY_PUBLIC_KEY,
+            1,
+            MIN_FEE,
+            0,
+            TRANSACTION_LIFETIME_BLOCKS,
+            data,
+        );
+
+        // A transaction of the same sender competing for the sequence number
+        let mut competing = transaction.clone();
+        competing.fee += 1;
+
+        state.put_transaction_mempool(transaction.clone()).unwrap();
+        state.put_transaction_mempool(competing).unwrap();
+        assert_eq!(state.pending_transactions(&EMPTY_ADDRESS), 2);
+
+        push_block(&mut state, EMPTY_PUBLIC_KEY, vec![transaction]).unwrap();
+
+        assert!(state.mempool().is_empty());
+        assert_eq!(state.pending_transactions(&EMPTY_ADDRESS), 0);
+    }
+}
@@ -0,0 +1,287 @@
+use super::{Database, State};
+use crate::{block::Block, constants::*, primitive::*, transaction::MerkleTree};
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+
+impl State {
+    /// Rebuilding accounts, the state tree, the height index, the watched history and the
+    /// transaction index by replaying the stored blocks
+    pub fn reindex(&mut self) -> Result<()> {
+        if self.database.get_base_height()? > 0 {
+            return Err(anyhow!(
+                "Blocks of a snapshot or a pruned database cannot be replayed"
+            ));
+        }
+
+        // The last block is saved so that an interrupted reindexing continues up to it
+        let last_hash = match self.database.get_reindex_header()? {
+            Some(hash) => hash,
+            None => {
+                let hash = self.last_header.hash()?;
+                self.database.put_reindex_header(Some(hash))?;
+                hash
+            }
+        };
+
+        // Hashes of the main chain by the references to the previous blocks
+        let mut hashes = vec![];
+        let mut header = self.database.get_block_header_from_hash(last_hash)?;
+        while header.height > 0 {
+            hashes.push(header.hash()?);
+            header = self
+                .database
+                .get_block_header_from_hash(header.prev_block)?;
+        }
+        hashes.reverse();
+
+        for cf in [
+            ACCOUNTS,
+            ACCOUNTS_PUBLIC_KEY,
+            STATE_TREE,
+            BLOCK_HEADERS_HASH,
+            WATCHED_HISTORY,
+        ] {
+            self.database.clear(cf)?;
+        }
+
+        // Replaying from the genesis block
+        let mut batch = self.database.create_batch();
+        self.database.put_block_header(&mut batch, &header)?;
+        self.database.write(batch)?;
+
+        self.last_header = header;
+        self.lwma_calculate(0)?;
+
+        let mut blocks = HashSet::from([self.last_header.hash()?]);
+        let mut transactions = HashSet::new();
+
+        let last_height = hashes.len() as u64;
+        for (height, hash) in (1..).zip(hashes) {
+            let block = self.database.get_block_from_hash(hash)?;
+            self.put_block(&block)?;
+
+            blocks.insert(hash);
+            for transaction in &block.transactions.0 {
+                transactions.insert(transaction.hash()?);
+            }
+
+            if height % PROGRESS_INTERVAL == 0 {
+                log::info!("Reindexed blocks: {height}/{last_height}");
+            }
+        }
+
+        // Transactions of the blocks off the main chain are no longer known to the chain
+        let mut batch = self.database.create_batch();
+        for (hash, block_transactions) in self.database.get_stored_block_transactions()? {
+            if blocks.contains(&hash) {
+                continue;
+            }
+
+            let fork_transactions: Vec<Hash> = block_transactions
+                .into_iter()
+                .filter(|hash| !transactions.contains(hash))
+                .collect();
+            self.database
+                .delete_fork_transactions(&mut batch, hash, &fork_transactions)?;
+        }
+        self.database.write(batch)?;
+
+        self.database.put_reindex_header(None)?;
+        log::info!(
+            "Reindexing is completed at height {}",
+            self.last_header.height
+        );
+
+        Ok(())
+    }
+
+    /// Revalidating signatures, Merkle roots, mining targets and PoW of the last blocks.
+    /// The chain ids and the state roots of all blocks are checked by replaying them on an
+    /// empty state, the error reports the first invalid block
+    pub fn verify_chain(&self, count: u64) -> Result<()> {
+        if self.database.get_base_height()? > 0 {
+            return Err(anyhow!(
+                "Blocks of a snapshot or a pruned database cannot be replayed"
+            ));
+        }
+
+        let last_height = self.last_header.height;
+        let start = std::cmp::max(1, (last_height + 1).saturating_sub(count));
+
+        let mut replay = State::from_database(Database::memory()?, self.network)?;
+        if replay.last_header.hash()? != self.database.get_block_header_from_height(0)?.hash()? {
+            return Err(anyhow!("Genesis block does not match"));
+        }
+
+        for height in 1..=last_height {
+            let block = self.database.get_block_from_height(height)?;
+
+            let mut result = block.check_state(&replay);
+            if result.is_ok() && height >= start {
+                result = self.verify_block(&block);
+            }
+            result.map_err(|error| anyhow!("Block {height} is not valid: {error}"))?;
+
+            replay.put_block(&block)?;
+
+            if height % PROGRESS_INTERVAL == 0 {
+                log::info!("Verified blocks: {height}/{last_height}");
+            }
+        }
+
+        log::info!("Verified blocks from {start} to {last_height}");
+
+        Ok(())
+    }
+
+    fn verify_block(&self, block: &Block) -> Result<()> {
+        let header = &block.header;
+        let height = header.height;
+        let prev_header = self.database.get_block_header_from_height(height - 1)?;
+
+        if header.prev_block != prev_header.hash()? {
+            return Err(anyhow!("Header does not refer to the previous header"));
+        }
+
+        header.signature_verify()?;
+
+        if header.transactions_count != block.transactions.len() {
+            return Err(anyhow!("Number of transactions does not match"));
+        }
+        if !MerkleTree::verify(&block.transactions, header.root)? {
+            return Err(anyhow!("Merkle tree hash does not match"));
+        }
+        for transaction in &block.transactions.0 {
+            transaction.signature_verify()?;
+        }
+
+        // Mining target from the headers preceding the block
+        let count = std::cmp::min(height - 1, LWMA_NUMBER_BLOCKS);
+        let headers = self
+            .database
+            .get_block_headers_from_height(height - 1, count)?;
+        let mut lwma1 = self.lwma1.clone();
+        lwma1.calculate(headers)?;
+
+        if header.n_bits != lwma1.get_target_u32() {
+            return Err(anyhow!("Invalid mining target"));
+        }
+
+        let mut pow_header = header.clone();
+        pow_header.pow_hash(&self.create_randomx_vm_from_height(height - 1)?)?;
+
+        if pow_header.pow_hash != header.pow_hash {
+            return Err(anyhow!("PoW hash does not match"));
+        }
+        if U256::from(header.pow_hash.as_slice()) > lwma1.get_target() {
+            return Err(anyhow!("PoW hash does not satisfy the target"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        account::Account,
+        state::{
+            push_block, push_empty_blocks,
+            watch::{HistoryEntry, HistoryEvent},
+        },
+        transaction::{Data, Transaction, Transactions},
+    };
+
+    #[test]
+    fn reindex_accounts() {
+        let mut state =
+            State::from_database(Database::memory().unwrap(), Network::Testnet).unwrap();
+        let generator = Account::from_public_key(EMPTY_PUBLIC_KEY, Network::Testnet).address;
+        state.watch_address(generator, "").unwrap();
+        push_empty_blocks(&mut state, EMPTY_PUBLIC_KEY, 3).unwrap();
+        let last_hash = state.last_header.hash().unwrap();
+        let history = state.history(generator).unwrap();
+
+        // A transaction of a block off the main chain and a stale history entry
+        let data = Data::RotatePublicKey {
+            public_key: EMPTY_PUBLIC_KEY,
+        };
+        let transaction = Transaction::new(
+            state.chain_id(),
+            EMPTY_ADDRESS,
+            EMPTY_PUBLIC_KEY,
+            1,
+            MIN_FEE,
+            0,
+            TRANSACTION_LIFETIME_BLOCKS,
+            data,
+        );
+        let transaction_hash = transaction.hash().unwrap();
+        let mut transactions = Transactions::default();
+        transactions.push(transaction);
+
+        let mut batch = state.database.create_batch();
+        state
+            .database
+            .put_block_transactions(&mut batch, [1; 32], &transactions)
+            .unwrap();
+        let entry = HistoryEntry {
+            height: 4,
+            block: [1; 32],
+            event: HistoryEvent::Reward { amount: 100 },
+        };
+        state
+            .database
+            .put_history(&mut batch, generator, 4, &[entry])
+            .unwrap();
+        state.database.write(batch).unwrap();
+
+        state.database.clear(ACCOUNTS).unwrap();
+        state.reindex().unwrap();
+
+        assert_eq!(state.last_header.hash().unwrap(), last_hash);
+        assert_eq!(state.database.get_reindex_header().unwrap(), None);
+        assert_eq!(
+            state
+                .database
+                .get_account_from_public_key(EMPTY_PUBLIC_KEY)
+                .unwrap()
+                .balance,
+            300
+        );
+        assert_eq!(state.history(generator).unwrap(), history);
+        assert!(!state.contains_transaction(&transaction_hash));
+    }
+
+    #[test]
+    fn verify_state_roots() {
+        let mut state =
+            State::from_database(Database::memory().unwrap(), Network::Testnet).unwrap();
+        push_empty_blocks(&mut state, EMPTY_PUBLIC_KEY, 2).unwrap();
+        state.verify_chain(0).unwrap();
+
+        // A stored block whose state root does not follow from the previous block
+        let mut block = push_block(&mut state, EMPTY_PUBLIC_KEY, vec![]).unwrap();
+        block.header.state_root = EMPTY_HASH;
+
+        let mut batch = state.database.create_batch();
+        state
+            .database
+            .put_block_header(&mut batch, &block.header)
+            .unwrap();
+        state
+            .database
+            .put_block_transactions(
+                &mut batch,
+                block.header.hash().unwrap(),
+                &block.transactions,
+            )
+            .unwrap();
+        state.database.write(batch).unwrap();
+        state.last_header = block.header;
+
+        let error = state.verify_chain(0).unwrap_err();
+        assert!(error.to_string().starts_with("Block 3 is not valid"));
+    }
+}
@@ -0,0 +1,182 @@
+use super::{database::Database, tree::StateTree, State};
+use crate::{account::Account, block::Header, constants::*, primitive::*};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Account state at the last block for bootstrapping a new node without replaying the chain
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Snapshot {
+    /// Headers of the LWMA window ending with the snapshot block and the RandomX key header
+    pub headers: Vec<Header>,
+    pub accounts: Vec<Account>,
+}
+
+impl Snapshot {
+    /// Creating a snapshot of the state at the last block
+    pub fn from_state(state: &State) -> Result<Self> {
+        let height = state.last_header.height;
+        let count = std::cmp::min(height, LWMA_NUMBER_BLOCKS);
+
+        let mut headers = vec![];
+
+        let key_height = height.div_euclid(RANDOMX_CHANGE_KEY) * RANDOMX_CHANGE_KEY;
+        if key_height < height - count {
+            headers.push(state.database.get_block_header_from_height(key_height)?);
+        }
+        headers.extend(
+            state
+                .database
+                .get_block_headers_from_height(height, count)?,
+        );
+
+        let mut accounts = vec![];
+        for address in state.state_tree().addresses(state.last_header.state_root)? {
+            accounts.push(state.database.get_account_from_address(address)?);
+        }
+
+        Ok(Self { headers, accounts })
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let bytes = bincode::serialize(self)
+            .map_err(|error| anyhow!("Failed to serialize snapshot: {error:?}"))?;
+
+        fs::write(path, bytes).map_err(|error| anyhow!("Failed to write snapshot {path}: {error}"))
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let bytes =
+            fs::read(path).map_err(|error| anyhow!("Failed to read snapshot {path}: {error}"))?;
+
+        bincode::deserialize(&bytes)
+            .map_err(|error| anyhow!("Failed to deserialize snapshot: {error:?}"))
+    }
+
+    /// Last header of the snapshot
+    pub fn header(&self) -> Result<&Header> {
+        self.headers
+            .last()
+            .ok_or_else(|| anyhow!("Snapshot does not contain headers"))
+    }
+
+    /// Importing the snapshot into an empty database. The accounts are verified against
+    /// the state root of the last header, and the last header against the trusted hash
+    pub fn import(&self, database: &Database, trusted_hash: Hash) -> Result<()> {
+        if !database.is_empty()? {
+            return Err(anyhow!(
+                "Snapshot can only be imported into an empty database"
+            ));
+        }
+
+        let header = self.header()?;
+
+        if header.hash()? != trusted_hash {
+            return Err(anyhow!(
+                "Snapshot block hash does not match the trusted hash"
+            ));
+        }
+
+        // The LWMA window ends with the trusted header, its headers follow each other
+        let count = std::cmp::min(header.height, LWMA_NUMBER_BLOCKS) as usize;
+        if self.headers.len() < count + 1 {
+            return Err(anyhow!("Snapshot does not contain the LWMA window headers"));
+        }
+        let (leading, window) = self.headers.split_at(self.headers.len() - count - 1);
+
+        for pair in window.windows(2) {
+            if pair[1].height != pair[0].height + 1 || pair[1].prev_block != pair[0].hash()? {
+                return Err(anyhow!(
+                    "Snapshot headers do not form a chain: {}",
+                    pair[1].height
+                ));
+            }
+        }
+
+        // Only the RandomX key header may precede the window
+        let key_height = header.height.div_euclid(RANDOMX_CHANGE_KEY) * RANDOMX_CHANGE_KEY;
+        match leading {
+            [] => {}
+            [key_header] if key_header.height == key_height && key_height < window[0].height => {}
+            _ => {
+                return Err(anyhow!(
+                    "Snapshot headers before the LWMA window are not the RandomX key header"
+                ))
+            }
+        }
+
+        let mut batch = database.create_batch();
+
+        let mut leaves = vec![];
+        for account in self.accounts.iter() {
+            database.put_account(&mut batch, account)?;
+            leaves.push((account.address, StateTree::leaf_hash(account)?));
+        }
+
+        let state_root = StateTree::new(database).update(&mut batch, EMPTY_HASH, leaves)?;
+        if state_root != header.state_root {
+            return Err(anyhow!("Snapshot accounts do not match the state root"));
+        }
+
+        // The last header is written last to become the last block of the chain
+        for header in self.headers.iter() {
+            database.put_block_header(&mut batch, header)?;
+        }
+        database.put_base_height(&mut batch, header.height)?;
+
+        database.write(batch)?;
+
+        log::info!(
+            "Snapshot is imported at height {} with {} accounts",
+            header.height,
+            self.accounts.len()
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::push_empty_blocks;
+
+    #[test]
+    fn export_and_import() {
+        let mut state =
+            State::from_database(Database::memory().unwrap(), Network::Testnet).unwrap();
+
+        push_empty_blocks(&mut state, EMPTY_PUBLIC_KEY, 3).unwrap();
+
+        let mut snapshot = Snapshot::from_state(&state).unwrap();
+        let hash = state.last_header.hash().unwrap();
+
+        let database = Database::memory().unwrap();
+        assert!(snapshot.import(&database, EMPTY_HASH).is_err());
+        snapshot.import(&database, hash).unwrap();
+
+        let imported = State::from_database(database, Network::Testnet).unwrap();
+        assert_eq!(imported.last_header.hash().unwrap(), hash);
+
+        snapshot.accounts[0].balance += 1;
+        assert!(snapshot.import(&Database::memory().unwrap(), hash).is_err());
+        snapshot.accounts[0].balance -= 1;
+
+        // A window header replaced or left out breaks the chain to the trusted header
+        let header = snapshot.headers[1].clone();
+        snapshot.headers[1].timestamp += 1;
+        assert!(snapshot.import(&Database::memory().unwrap(), hash).is_err());
+        snapshot.headers[1] = header;
+
+        let mut gap = Snapshot::from_state(&state).unwrap();
+        gap.headers.remove(1);
+        assert!(gap.import(&Database::memory().unwrap(), hash).is_err());
+
+        // A leading header is accepted only as the RandomX key header
+        let mut leading = Snapshot::from_state(&state).unwrap();
+        leading.headers.insert(0, leading.headers[0].clone());
+        assert!(leading.import(&Database::memory().unwrap(), hash).is_err());
+
+        snapshot.import(&Database::memory().unwrap(), hash).unwrap();
+    }
+}
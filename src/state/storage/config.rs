@@ -0,0 +1,164 @@
+use crate::constants::*;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::{collections::HashMap, fs};
+
+const MIB: usize = 1024 * 1024;
+
+/// RocksDB configuration loaded from a TOML file. The profile provides the base values,
+/// which are overridden by the values set in the file
+///
+/// ```toml
+/// profile = "low-memory"
+/// block_cache_size = 16777216
+///
+/// [column_families.transactions]
+/// compression = "zstd"
+/// bloom_filter_bits = 10.0
+/// ```
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct RocksDbConfig {
+    pub profile: Profile,
+    /// Size of the block cache shared by all column families in bytes
+    pub block_cache_size: Option<usize>,
+    pub max_open_files: Option<i32>,
+    pub column_families: HashMap<String, ColumnFamilyConfig>,
+}
+
+/// Base values of the configuration
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    /// 64 MiB block cache, 64 MiB write buffers, LZ4 and Zstd for block bodies
+    #[default]
+    Default,
+    /// 8 MiB block cache, 4 MiB write buffers, Zstd and at most 64 open files for small nodes
+    LowMemory,
+}
+
+/// Column family values overriding the profile
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnFamilyConfig {
+    pub compression: Option<Compression>,
+    /// Bits per key of the bloom filter, 0 disables the filter
+    pub bloom_filter_bits: Option<f64>,
+    pub write_buffer_size: Option<usize>,
+    pub max_write_buffer_number: Option<i32>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    None,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+/// Resolved options of the column family
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnFamilyOptions {
+    pub compression: Compression,
+    pub bloom_filter_bits: Option<f64>,
+    pub write_buffer_size: usize,
+    pub max_write_buffer_number: i32,
+}
+
+impl RocksDbConfig {
+    pub fn load(path: &str) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| anyhow!("Failed to read database config {path}: {error}"))?;
+
+        toml::from_str(&text).map_err(|error| anyhow!("Invalid database config {path}: {error}"))
+    }
+
+    pub fn block_cache_size(&self) -> usize {
+        self.block_cache_size.unwrap_or(match self.profile {
+            Profile::Default => 64 * MIB,
+            Profile::LowMemory => 8 * MIB,
+        })
+    }
+
+    pub fn max_open_files(&self) -> i32 {
+        self.max_open_files.unwrap_or(match self.profile {
+            Profile::Default => -1,
+            Profile::LowMemory => 64,
+        })
+    }
+
+    /// Options of the column family with the overrides applied
+    pub fn column_family(&self, name: &str) -> ColumnFamilyOptions {
+        // Block bodies are large and rarely read, so they get the stronger compression
+        let compression = match (self.profile, name) {
+            (Profile::LowMemory, _) | (_, BLOCK_TRANSACTIONS | TRANSACTIONS) => Compression::Zstd,
+            _ => Compression::Lz4,
+        };
+
+        // Bloom filters speed up point lookups of random hash keys
+        let bloom_filter_bits = match name {
+            BLOCK_HEADERS_HASH | INFO => None,
+            _ => Some(10.0),
+        };
+
+        let mut options = ColumnFamilyOptions {
+            compression,
+            bloom_filter_bits,
+            write_buffer_size: match self.profile {
+                Profile::Default => 64 * MIB,
+                Profile::LowMemory => 4 * MIB,
+            },
+            max_write_buffer_number: 2,
+        };
+
+        if let Some(config) = self.column_families.get(name) {
+            if let Some(compression) = config.compression {
+                options.compression = compression;
+            }
+            if let Some(bits) = config.bloom_filter_bits {
+                options.bloom_filter_bits = if bits > 0.0 { Some(bits) } else { None };
+            }
+            if let Some(size) = config.write_buffer_size {
+                options.write_buffer_size = size;
+            }
+            if let Some(number) = config.max_write_buffer_number {
+                options.max_write_buffer_number = number;
+            }
+        }
+
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_overrides() {
+        let config: RocksDbConfig = toml::from_str(
+            r#"
+            profile = "low-memory"
+
+            [column_families.accounts]
+            bloom_filter_bits = 0.0
+            write_buffer_size = 1024
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.block_cache_size(), 8 * MIB);
+
+        let accounts = config.column_family(ACCOUNTS);
+        assert_eq!(accounts.compression, Compression::Zstd);
+        assert_eq!(accounts.bloom_filter_bits, None);
+        assert_eq!(accounts.write_buffer_size, 1024);
+
+        let transactions = config.column_family(TRANSACTIONS);
+        assert_eq!(transactions.bloom_filter_bits, Some(10.0));
+        assert_eq!(transactions.write_buffer_size, 4 * MIB);
+
+        assert!(toml::from_str::<RocksDbConfig>("unknown = 1").is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use super::{config::ColumnFamilyOptions, Batch, Compression, RocksDbConfig, Storage};
+use crate::constants::*;
+use anyhow::{anyhow, Result};
+use rocksdb::{
+    BlockBasedOptions, Cache, ColumnFamilyDescriptor, DBCompressionType, Direction, IteratorMode,
+    Options, WriteBatch, DB,
+};
+
+/// Column families opened by the storage
+const COLUMN_FAMILIES: [&str; 10] = [
+    BLOCK_HEADERS,
+    BLOCK_HEADERS_HASH,
+    BLOCK_TRANSACTIONS,
+    TRANSACTIONS,
+    ACCOUNTS,
+    ACCOUNTS_PUBLIC_KEY,
+    STATE_TREE,
+    WATCHED,
+    WATCHED_HISTORY,
+    INFO,
+];
+
+/// RocksDB storage
+pub struct RocksDbStorage {
+    db: DB,
+}
+
+impl RocksDbStorage {
+    pub fn open(path: &str, config: &RocksDbConfig) -> Result<Self> {
+        let mut options = Options::default();
+        options.create_missing_column_families(true);
+        options.create_if_missing(true);
+        options.set_max_open_files(config.max_open_files());
+
+        let db = DB::open_cf_descriptors(&options, path, Self::descriptors(config))
+            .map_err(|error| anyhow!("Failed to open the database {path}: {error}"))?;
+
+        Ok(Self { db })
+    }
+
+    fn descriptors(config: &RocksDbConfig) -> Vec<ColumnFamilyDescriptor> {
+        let cache = Cache::new_lru_cache(config.block_cache_size());
+
+        COLUMN_FAMILIES
+            .into_iter()
+            .map(|name| {
+                let options = Self::options(&config.column_family(name), &cache);
+                ColumnFamilyDescriptor::new(name, options)
+            })
+            .collect()
+    }
+
+    fn options(config: &ColumnFamilyOptions, cache: &Cache) -> Options {
+        let mut block_options = BlockBasedOptions::default();
+        block_options.set_block_cache(cache);
+        if let Some(bits) = config.bloom_filter_bits {
+            block_options.set_bloom_filter(bits, false);
+        }
+
+        let mut options = Options::default();
+        options.set_block_based_table_factory(&block_options);
+        options.set_compression_type(match config.compression {
+            Compression::None => DBCompressionType::None,
+            Compression::Snappy => DBCompressionType::Snappy,
+            Compression::Lz4 => DBCompressionType::Lz4,
+            Compression::Zstd => DBCompressionType::Zstd,
+        });
+        options.set_write_buffer_size(config.write_buffer_size);
+        options.set_max_write_buffer_number(config.max_write_buffer_number);
+
+        options
+    }
+}
+
+impl Storage for RocksDbStorage {
+    fn get(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let cf = self
+            .db
+            .cf_handle(cf)
+            .ok_or_else(|| anyhow!("Failed column family handle"))?;
+
+        self.db
+            .get_cf(cf, key)
+            .map_err(|error| anyhow!("Failed to reading data from the database: {error}"))
+    }
+
+    fn multi_get(&self, cf: &str, keys: Vec<&[u8]>) -> Result<Vec<Option<Vec<u8>>>> {
+        let cf = self
+            .db
+            .cf_handle(cf)
+            .ok_or_else(|| anyhow!("Failed column family handle"))?;
+        let keys = keys.into_iter().map(|key| (&cf, key)).collect::<Vec<_>>();
+
+        self.db
+            .multi_get_cf(keys)
+            .into_iter()
+            .map(|item| {
+                item.map_err(|error| anyhow!("Failed to reading data from the database: {error}"))
+            })
+            .collect()
+    }
+
+    fn range(&self, cf: &str, from: &[u8], to: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let cf = self
+            .db
+            .cf_handle(cf)
+            .ok_or_else(|| anyhow!("Failed column family handle"))?;
+
+        let mut values = vec![];
+        for item in self
+            .db
+            .iterator_cf(cf, IteratorMode::From(from, Direction::Forward))
+        {
+            let (key, value) =
+                item.map_err(|error| anyhow!("Failed to reading data from the database: {error}"))?;
+            if key.as_ref() >= to {
+                break;
+            }
+            values.push((key.to_vec(), value.to_vec()));
+        }
+
+        Ok(values)
+    }
+
+    fn write(&self, batch: Batch) -> Result<()> {
+        let mut write_batch = WriteBatch::default();
+
+        for (cf, key, value) in batch.into_operations() {
+            let cf = self
+                .db
+                .cf_handle(cf)
+                .ok_or_else(|| anyhow!("Failed column family handle"))?;
+
+            match value {
+                Some(value) => write_batch.put_cf(cf, key, value),
+                None => write_batch.delete_cf(cf, key),
+            }
+        }
+
+        self.db
+            .write(write_batch)
+            .map_err(|error| anyhow!("Failed to write to the database: {error:?}"))
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.db
+            .flush_wal(true)
+            .map_err(|error| anyhow!("Failed to flush the WAL: {error}"))?;
+
+        // Each column family has its own memtable
+        for name in COLUMN_FAMILIES {
+            let cf = self
+                .db
+                .cf_handle(name)
+                .ok_or_else(|| anyhow!("Failed column family handle"))?;
+
+            self.db
+                .flush_cf(cf)
+                .map_err(|error| anyhow!("Failed to flush the database: {error}"))?;
+        }
+
+        Ok(())
+    }
+
+    fn clear(&self, cf: &str) -> Result<()> {
+        let cf = self
+            .db
+            .cf_handle(cf)
+            .ok_or_else(|| anyhow!("Failed column family handle"))?;
+
+        // Keys are deleted in chunks so the write batch of a large column family stays small
+        let mut write_batch = WriteBatch::default();
+        let mut count = 0;
+        for item in self.db.iterator_cf(cf, IteratorMode::Start) {
+            let (key, _) =
+                item.map_err(|error| anyhow!("Failed to reading data from the database: {error}"))?;
+            write_batch.delete_cf(cf, key);
+            count += 1;
+
+            if count == CLEAR_BATCH_SIZE {
+                self.db
+                    .write(std::mem::take(&mut write_batch))
+                    .map_err(|error| anyhow!("Failed to write to the database: {error:?}"))?;
+                count = 0;
+            }
+        }
+
+        self.db
+            .write(write_batch)
+            .map_err(|error| anyhow!("Failed to write to the database: {error:?}"))
+    }
+}
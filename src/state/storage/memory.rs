@@ -0,0 +1,68 @@
+use super::{Batch, Storage};
+use anyhow::{anyhow, Result};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::RwLock,
+};
+
+type ColumnFamily = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// In-memory storage for tests and embedding, the data is lost when dropped
+#[derive(Default)]
+pub struct MemoryStorage {
+    data: RwLock<HashMap<String, ColumnFamily>>,
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let data = self
+            .data
+            .read()
+            .map_err(|_| anyhow!("Failed to lock the storage"))?;
+
+        Ok(data.get(cf).and_then(|values| values.get(key).cloned()))
+    }
+
+    fn range(&self, cf: &str, from: &[u8], to: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let data = self
+            .data
+            .read()
+            .map_err(|_| anyhow!("Failed to lock the storage"))?;
+
+        Ok(data.get(cf).map_or_else(Vec::new, |values| {
+            values
+                .range(from.to_vec()..to.to_vec())
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect()
+        }))
+    }
+
+    fn write(&self, batch: Batch) -> Result<()> {
+        let mut data = self
+            .data
+            .write()
+            .map_err(|_| anyhow!("Failed to lock the storage"))?;
+
+        for (cf, key, value) in batch.into_operations() {
+            let values = data.entry(cf.to_string()).or_default();
+
+            match value {
+                Some(value) => values.insert(key, value),
+                None => values.remove(&key),
+            };
+        }
+
+        Ok(())
+    }
+
+    fn clear(&self, cf: &str) -> Result<()> {
+        let mut data = self
+            .data
+            .write()
+            .map_err(|_| anyhow!("Failed to lock the storage"))?;
+
+        data.remove(cf);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+mod config;
+mod disk;
+mod memory;
+
+pub use config::{ColumnFamilyConfig, Compression, Profile, RocksDbConfig};
+pub use disk::RocksDbStorage;
+pub use memory::MemoryStorage;
+
+use anyhow::Result;
+
+/// Key-value storage of the blockchain state, divided into column families
+pub trait Storage: Send + Sync {
+    /// Getting the value of the key from the column family
+    fn get(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// Getting the values of several keys from the column family
+    fn multi_get(&self, cf: &str, keys: Vec<&[u8]>) -> Result<Vec<Option<Vec<u8>>>> {
+        keys.into_iter().map(|key| self.get(cf, key)).collect()
+    }
+
+    /// Getting the keys and values of the column family from `from` up to `to` exclusive,
+    /// in the order of the keys
+    fn range(&self, cf: &str, from: &[u8], to: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
+
+    /// Atomic writing of the batch
+    fn write(&self, batch: Batch) -> Result<()>;
+
+    /// Deleting all keys of the column family
+    fn clear(&self, cf: &str) -> Result<()>;
+
+    /// Persisting the written data before closing
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Write of the value to the column family, `None` deletes the key
+type Operation = (&'static str, Vec<u8>, Option<Vec<u8>>);
+
+/// Set of writes applied atomically
+#[derive(Default)]
+pub struct Batch {
+    operations: Vec<Operation>,
+}
+
+impl Batch {
+    pub fn put(&mut self, cf: &'static str, key: &[u8], value: &[u8]) {
+        self.operations
+            .push((cf, key.to_vec(), Some(value.to_vec())));
+    }
+
+    pub fn delete(&mut self, cf: &'static str, key: &[u8]) {
+        self.operations.push((cf, key.to_vec(), None));
+    }
+
+    fn into_operations(self) -> impl Iterator<Item = Operation> {
+        self.operations.into_iter()
+    }
+}
@@ -0,0 +1,248 @@
+use super::{database::Database, storage::Batch};
+use crate::{account::Account, constants::*, primitive::*};
+use anyhow::{anyhow, Result};
+use blake2::Digest;
+
+/// Depth of the tree, one level for each bit of the address
+const TREE_DEPTH: usize = 256;
+
+/// Sparse Merkle tree of accounts keyed by address. Empty subtrees hash to `EMPTY_HASH`,
+/// so only the nodes on the paths to existing accounts are stored. The nodes replaced by
+/// an update are deleted, only the tree of the last root is kept
+pub struct StateTree<'a> {
+    database: &'a Database,
+}
+
+impl<'a> StateTree<'a> {
+    pub fn new(database: &'a Database) -> Self {
+        Self { database }
+    }
+
+    /// Leaf hash of the account
+    pub fn leaf_hash(account: &Account) -> Result<Hash> {
+        let bytes = bincode::serialize(account)
+            .map_err(|error| anyhow!("Failed to serialize account: {error:?}"))?;
+
+        let mut hasher = Blake2b256::new();
+        hasher.update([0u8]);
+        hasher.update(account.address);
+        hasher.update(bytes);
+
+        Ok(hasher.finalize().into())
+    }
+
+    /// Updating the leaves of the tree, the new nodes are added to the batch
+    pub fn update(
+        &self,
+        batch: &mut Batch,
+        root: Hash,
+        mut leaves: Vec<(Address, Hash)>,
+    ) -> Result<Hash> {
+        leaves.sort_by_key(|(address, _)| *address);
+        leaves.dedup_by(|a, b| a.0 == b.0);
+
+        self.update_node(batch, root, 0, &leaves)
+    }
+
+    /// Getting the sibling hashes on the path from the root to the address
+    pub fn proof(&self, root: Hash, address: &Address) -> Result<Vec<Hash>> {
+        let mut node = root;
+        let mut proof = Vec::with_capacity(TREE_DEPTH);
+
+        for depth in 0..TREE_DEPTH {
+            let (left, right) = self.children(node)?;
+
+            if bit(address, depth) {
+                proof.push(left);
+                node = right;
+            } else {
+                proof.push(right);
+                node = left;
+            }
+        }
+
+        Ok(proof)
+    }
+
+    /// Verifying the leaf hash of the address against the root,
+    /// `EMPTY_HASH` as the leaf proves that the account does not exist
+    pub fn verify(root: Hash, address: &Address, leaf: Hash, proof: &[Hash]) -> bool {
+        if proof.len() != TREE_DEPTH {
+            return false;
+        }
+
+        let mut node = leaf;
+        for (depth, sibling) in proof.iter().enumerate().rev() {
+            node = if bit(address, depth) {
+                node_hash(sibling, &node)
+            } else {
+                node_hash(&node, sibling)
+            };
+        }
+
+        node == root
+    }
+
+    /// Getting the addresses of all accounts in the tree
+    pub fn addresses(&self, root: Hash) -> Result<Vec<Address>> {
+        let mut addresses = vec![];
+        let mut path = EMPTY_ADDRESS;
+        self.collect_addresses(root, 0, &mut path, &mut addresses)?;
+
+        Ok(addresses)
+    }
+
+    fn collect_addresses(
+        &self,
+        node: Hash,
+        depth: usize,
+        path: &mut Address,
+        addresses: &mut Vec<Address>,
+    ) -> Result<()> {
+        if node == EMPTY_HASH {
+            return Ok(());
+        }
+        if depth == TREE_DEPTH {
+            addresses.push(*path);
+            return Ok(());
+        }
+
+        let (left, right) = self.children(node)?;
+        let mask = 1 << (7 - depth % 8);
+
+        path[depth / 8] &= !mask;
+        self.collect_addresses(left, depth + 1, path, addresses)?;
+
+        path[depth / 8] |= mask;
+        self.collect_addresses(right, depth + 1, path, addresses)?;
+        path[depth / 8] &= !mask;
+
+        Ok(())
+    }
+
+    fn update_node(
+        &self,
+        batch: &mut Batch,
+        node: Hash,
+        depth: usize,
+        leaves: &[(Address, Hash)],
+    ) -> Result<Hash> {
+        if leaves.is_empty() {
+            return Ok(node);
+        }
+        if depth == TREE_DEPTH {
+            return Ok(leaves[0].1);
+        }
+
+        let (left, right) = self.children(node)?;
+        let split = leaves.partition_point(|(address, _)| !bit(address, depth));
+
+        let left = self.update_node(batch, left, depth + 1, &leaves[..split])?;
+        let right = self.update_node(batch, right, depth + 1, &leaves[split..])?;
+
+        // Leaf hashes commit to the address, so a node is referenced only from its position
+        // and the replaced node belongs to the previous root alone
+        let hash = node_hash(&left, &right);
+        if hash != node {
+            if node != EMPTY_HASH {
+                self.database.delete_tree_node(batch, node)?;
+            }
+            if hash != EMPTY_HASH {
+                self.database.put_tree_node(batch, hash, left, right)?;
+            }
+        }
+
+        Ok(hash)
+    }
+
+    fn children(&self, node: Hash) -> Result<(Hash, Hash)> {
+        if node == EMPTY_HASH {
+            Ok((EMPTY_HASH, EMPTY_HASH))
+        } else {
+            self.database.get_tree_node(node)
+        }
+    }
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    if *left == EMPTY_HASH && *right == EMPTY_HASH {
+        return EMPTY_HASH;
+    }
+
+    let mut hasher = Blake2b256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+
+    hasher.finalize().into()
+}
+
+/// Bit of the address at the depth, starting from the most significant
+fn bit(address: &Address, depth: usize) -> bool {
+    address[depth / 8] >> (7 - depth % 8) & 1 == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_and_prove() {
+        let database = Database::memory().unwrap();
+        let tree = StateTree::new(&database);
+
+        let first = Account::from_address(Address([1u8; 32]));
+        let mut second = Account::from_address(Address([2u8; 32]));
+        let first_leaf = StateTree::leaf_hash(&first).unwrap();
+
+        let mut batch = database.create_batch();
+        let root = tree
+            .update(
+                &mut batch,
+                EMPTY_HASH,
+                vec![
+                    (first.address, first_leaf),
+                    (second.address, StateTree::leaf_hash(&second).unwrap()),
+                ],
+            )
+            .unwrap();
+        database.write(batch).unwrap();
+
+        second.balance = 10;
+        let second_leaf = StateTree::leaf_hash(&second).unwrap();
+
+        let mut batch = database.create_batch();
+        let new_root = tree
+            .update(&mut batch, root, vec![(second.address, second_leaf)])
+            .unwrap();
+        database.write(batch).unwrap();
+        assert_ne!(root, new_root);
+
+        // The nodes of the previous root are replaced
+        assert!(database.get_tree_node(root).is_err());
+        assert!(database.get_tree_node(new_root).is_ok());
+
+        let proof = tree.proof(new_root, &first.address).unwrap();
+        assert!(StateTree::verify(
+            new_root,
+            &first.address,
+            first_leaf,
+            &proof
+        ));
+        assert!(!StateTree::verify(
+            new_root,
+            &first.address,
+            second_leaf,
+            &proof
+        ));
+
+        assert_eq!(
+            tree.addresses(new_root).unwrap(),
+            vec![first.address, second.address]
+        );
+
+        let missing = Address([3u8; 32]);
+        let proof = tree.proof(new_root, &missing).unwrap();
+        assert!(StateTree::verify(new_root, &missing, EMPTY_HASH, &proof));
+    }
+}
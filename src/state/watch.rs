@@ -0,0 +1,289 @@
+use super::{storage::Batch, State};
+use crate::{account::Account, block::Block, constants::*, primitive::*, transaction::Data};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Address watched by the node with its balance, the history of its blocks and transactions
+/// is stored as separate entries
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct WatchedAddress {
+    pub address: Address,
+    pub label: String,
+    pub balance: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub height: u64,
+    pub block: Hash,
+    pub event: HistoryEvent,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum HistoryEvent {
+    Reward {
+        amount: u64,
+    },
+    Received {
+        transaction: Hash,
+        sender: Address,
+        amount: u64,
+    },
+    Sent {
+        transaction: Hash,
+        recipient: Address,
+        amount: u64,
+        fee: u64,
+    },
+    RotatePublicKey {
+        transaction: Hash,
+        public_key: PublicKey,
+        fee: u64,
+    },
+}
+
+impl State {
+    /// Adding the address to the watched addresses, the history starts with the next block
+    /// or with a rescan
+    pub fn watch_address(&mut self, address: Address, label: &str) -> Result<()> {
+        let mut addresses = self.database.get_watched_addresses()?;
+        if addresses.contains(&address) {
+            return Err(anyhow!("Address is already watched"));
+        }
+        addresses.push(address);
+
+        let watched = WatchedAddress {
+            address,
+            label: label.to_string(),
+            balance: self.balance(address),
+        };
+
+        let mut batch = self.database.create_batch();
+        self.database.put_watched(&mut batch, &watched)?;
+        self.database
+            .put_watched_addresses(&mut batch, &addresses)?;
+        self.database.write(batch)
+    }
+
+    pub fn watched(&self) -> Result<Vec<WatchedAddress>> {
+        self.database
+            .get_watched_addresses()?
+            .into_iter()
+            .map(|address| self.database.get_watched(address))
+            .collect()
+    }
+
+    /// History of the watched address in the block order
+    pub fn history(&self, address: Address) -> Result<Vec<HistoryEntry>> {
+        self.database.get_history(address, 0)
+    }
+
+    /// Rebuilding the history of the watched addresses from the height, returns the number
+    /// of scanned blocks
+    pub fn rescan(&self, from_height: u64) -> Result<u64> {
+        self.check_rescan(from_height)?;
+
+        let mut height = from_height;
+        loop {
+            let scanned = self.rescan_blocks(height, RESCAN_CHUNK_SIZE)?;
+            if scanned == 0 {
+                return Ok(height - from_height);
+            }
+            height += scanned;
+        }
+    }
+
+    /// Checking that the blocks from the height are stored with their transactions
+    pub fn check_rescan(&self, from_height: u64) -> Result<()> {
+        let last_height = self.last_header.height;
+        if from_height > last_height {
+            return Err(anyhow!("Height is above the last block: {last_height}"));
+        }
+
+        let base_height = self.database.get_base_height()?;
+        if base_height > 0 && from_height <= base_height {
+            return Err(anyhow!(
+                "Block transactions up to height {base_height} are pruned"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilding the history of the watched addresses for at most `count` blocks from the
+    /// height, returns the number of scanned blocks, 0 above the last block. Blocks added
+    /// between the chunks record their history in `put_block`
+    pub fn rescan_blocks(&self, from_height: u64, count: u64) -> Result<u64> {
+        let last_height = self.last_header.height;
+        if from_height > last_height {
+            return Ok(0);
+        }
+        let to_height = std::cmp::min(last_height, from_height + count - 1);
+
+        let mut batch = self.database.create_batch();
+        let mut watched = self.watched()?;
+        for watched in watched.iter() {
+            self.database.delete_history(
+                &mut batch,
+                watched.address,
+                from_height,
+                to_height + 1,
+            )?;
+        }
+
+        for height in from_height..=to_height {
+            let block = self.database.get_block_from_height(height)?;
+
+            for watched in watched.iter() {
+                let entries = block_history(&block, watched.address)?;
+                self.database
+                    .put_history(&mut batch, watched.address, height, &entries)?;
+            }
+
+            if height % PROGRESS_INTERVAL == 0 {
+                log::info!("Rescanned blocks: {height}/{last_height}");
+            }
+        }
+
+        for watched in watched.iter_mut() {
+            watched.balance = self.balance(watched.address);
+            self.database.put_watched(&mut batch, watched)?;
+        }
+        self.database.write(batch)?;
+
+        Ok(to_height - from_height + 1)
+    }
+
+    /// Recording the committed block in the history of the watched addresses
+    pub(super) fn scan_watched(
+        &self,
+        batch: &mut Batch,
+        block: &Block,
+        accounts: &HashMap<Address, Account>,
+    ) -> Result<()> {
+        for address in self.database.get_watched_addresses()? {
+            let mut watched = self.database.get_watched(address)?;
+            let height = block.header.height;
+
+            // Entries of a replaced or replayed block are recorded again
+            self.database
+                .delete_history(batch, address, height, u64::MAX)?;
+            self.database
+                .put_history(batch, address, height, &block_history(block, address)?)?;
+
+            watched.balance = match accounts.get(&address) {
+                Some(account) => account.balance,
+                None => self.balance(address),
+            };
+
+            self.database.put_watched(batch, &watched)?;
+        }
+
+        Ok(())
+    }
+
+    fn balance(&self, address: Address) -> u64 {
+        self.database
+            .get_account_from_address(address)
+            .map_or(0, |account| account.balance)
+    }
+}
+
+/// History entries of the address in the block
+fn block_history(block: &Block, address: Address) -> Result<Vec<HistoryEntry>> {
+    let height = block.header.height;
+    let hash = block.header.hash()?;
+    let mut history = vec![];
+
+    let mut push = |event| {
+        history.push(HistoryEntry {
+            height,
+            block: hash,
+            event,
+        })
+    };
+
+    if block.header.generator == address {
+        push(HistoryEvent::Reward {
+            amount: block.header.reward,
+        });
+    }
+
+    for transaction in block.transactions.0.iter() {
+        let transaction_hash = transaction.hash()?;
+
+        match &transaction.data {
+            Data::Transfer {
+                recipient, amount, ..
+            } => {
+                if transaction.sender == address {
+                    push(HistoryEvent::Sent {
+                        transaction: transaction_hash,
+                        recipient: *recipient,
+                        amount: *amount,
+                        fee: transaction.fee,
+                    });
+                }
+                if *recipient == address {
+                    push(HistoryEvent::Received {
+                        transaction: transaction_hash,
+                        sender: transaction.sender,
+                        amount: *amount,
+                    });
+                }
+            }
+            Data::RotatePublicKey { public_key } => {
+                if transaction.sender == address {
+                    push(HistoryEvent::RotatePublicKey {
+                        transaction: transaction_hash,
+                        public_key: *public_key,
+                        fee: transaction.fee,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        state::{push_empty_blocks, Database},
+        wallet,
+    };
+
+    #[test]
+    fn watch_block_rewards() {
+        let mut state =
+            State::from_database(Database::memory().unwrap(), Network::Testnet).unwrap();
+        let (_, public_key) = wallet::generate();
+        let address = Account::from_public_key(public_key, Network::Testnet).address;
+        state.watch_address(address, "miner").unwrap();
+        assert!(state.watch_address(address, "").is_err());
+
+        push_empty_blocks(&mut state, public_key, 2).unwrap();
+
+        let watched = state.watched().unwrap();
+        let history = state.history(address).unwrap();
+        assert_eq!(watched[0].label, "miner");
+        assert_eq!(watched[0].balance, 200);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].height, 2);
+
+        // Rescanning replaces the entries of the scanned blocks
+        assert_eq!(state.rescan(2).unwrap(), 1);
+        assert_eq!(state.watched().unwrap(), watched);
+        assert_eq!(state.history(address).unwrap(), history);
+        assert!(state.rescan(3).is_err());
+
+        // A chunked rescan stops after the last block
+        assert_eq!(state.rescan_blocks(1, 1).unwrap(), 1);
+        assert_eq!(state.rescan_blocks(2, 5).unwrap(), 1);
+        assert_eq!(state.rescan_blocks(3, 5).unwrap(), 0);
+        assert_eq!(state.history(address).unwrap(), history);
+    }
+}
@@ -1,4 +1,10 @@
-use crate::{constants::*, swarm};
+use crate::{
+    block::{Block, Header},
+    constants::*,
+    primitive::*,
+    swarm::{self, limits::ConnectionLimits, limits::Metrics},
+    transaction::Transaction,
+};
 use async_std::io;
 use async_trait::async_trait;
 use libp2p::{
@@ -8,23 +14,29 @@ use libp2p::{
     swarm::NetworkBehaviour,
     PeerId,
 };
-use std::error::Error;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{convert::Infallible, error::Error, sync::Arc};
 
 #[derive(NetworkBehaviour)]
 #[behaviour(out_event = "BehaviourEvent")]
 pub struct Behaviour {
+    pub connection_limits: ConnectionLimits,
     pub gossipsub: gossipsub::Behaviour,
     pub identify: identify::Behaviour,
     pub mdns: mdns::async_io::Behaviour,
     pub request_response: request_response::Behaviour<SyncCodec>,
+    pub compact_block: request_response::Behaviour<CompactBlockCodec>,
+    pub transaction: request_response::Behaviour<TransactionCodec>,
 }
 
 impl Behaviour {
     pub async fn new(
         local_key: identity::Keypair,
         local_peer_id: PeerId,
+        metrics: Arc<Metrics>,
     ) -> Result<Self, Box<dyn Error>> {
         Ok(Self {
+            connection_limits: ConnectionLimits::new(metrics),
             gossipsub: gossipsub::Behaviour::new(
                 gossipsub::MessageAuthenticity::Signed(local_key.clone()),
                 gossipsub::ConfigBuilder::default().build()?,
@@ -39,6 +51,22 @@ impl Behaviour {
                 std::iter::once((SyncProtocol(), request_response::ProtocolSupport::Full)),
                 Default::default(),
             ),
+            compact_block: request_response::Behaviour::new(
+                CompactBlockCodec(),
+                std::iter::once((
+                    CompactBlockProtocol(),
+                    request_response::ProtocolSupport::Full,
+                )),
+                Default::default(),
+            ),
+            transaction: request_response::Behaviour::new(
+                TransactionCodec(),
+                std::iter::once((
+                    TransactionProtocol(),
+                    request_response::ProtocolSupport::Full,
+                )),
+                Default::default(),
+            ),
         })
     }
 }
@@ -48,6 +76,14 @@ pub enum BehaviourEvent {
     Mdns(mdns::Event),
     Identify(identify::Event),
     RequestResponse(request_response::Event<SyncRequest, SyncResponse>),
+    CompactBlock(request_response::Event<CompactBlockRequest, CompactBlockResponse>),
+    Transaction(request_response::Event<TransactionRequest, TransactionResponse>),
+}
+
+impl From<Infallible> for BehaviourEvent {
+    fn from(event: Infallible) -> Self {
+        match event {}
+    }
 }
 
 impl From<gossipsub::Event> for BehaviourEvent {
@@ -74,24 +110,83 @@ impl From<request_response::Event<SyncRequest, SyncResponse>> for BehaviourEvent
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct SyncRequest(pub Vec<u8>);
+impl From<request_response::Event<CompactBlockRequest, CompactBlockResponse>> for BehaviourEvent {
+    fn from(event: request_response::Event<CompactBlockRequest, CompactBlockResponse>) -> Self {
+        Self::CompactBlock(event)
+    }
+}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct SyncResponse(pub Vec<u8>);
+impl From<request_response::Event<TransactionRequest, TransactionResponse>> for BehaviourEvent {
+    fn from(event: request_response::Event<TransactionRequest, TransactionResponse>) -> Self {
+        Self::Transaction(event)
+    }
+}
+
+/// Synchronization request
+#[derive(Serialize, Deserialize, Debug)]
+pub enum SyncRequest {
+    /// Headers following the first known hash of the locator
+    GetHeaders { locator: Vec<Hash>, limit: u64 },
+    /// Blocks by hashes
+    GetBlocks(Vec<Hash>),
+    /// Blocks in the inclusive height range
+    GetBlockRange { start: u64, end: u64 },
+    /// Transaction by hash
+    GetTransaction(Hash),
+}
+
+/// Synchronization response
+#[derive(Serialize, Deserialize, Debug)]
+pub enum SyncResponse {
+    Headers(Vec<Header>),
+    Blocks(Vec<Block>),
+    Transaction(Option<Box<Transaction>>),
+    /// Transactions of blocks up to the base height are deleted by the responder
+    Pruned {
+        base_height: u64,
+    },
+    Refused(String),
+}
 
 #[derive(Debug, Clone)]
 pub struct SyncProtocol();
 
 impl ProtocolName for SyncProtocol {
     fn protocol_name(&self) -> &[u8] {
-        "/sync/1".as_bytes()
+        "/sync/2".as_bytes()
     }
 }
 
+/// Synchronization codec. Each message is prefixed with the protocol version
 #[derive(Clone)]
 pub struct SyncCodec();
 
+impl SyncCodec {
+    fn encode<M: Serialize>(message: &M) -> io::Result<Vec<u8>> {
+        let mut bytes = vec![SYNC_PROTOCOL_VERSION];
+
+        bincode::serialize_into(&mut bytes, message)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        Ok(bytes)
+    }
+
+    fn decode<M: DeserializeOwned>(bytes: &[u8]) -> io::Result<M> {
+        match bytes.split_first() {
+            Some((&SYNC_PROTOCOL_VERSION, payload)) => bincode::deserialize(payload)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error)),
+            Some((version, _)) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported sync protocol version: {version}"),
+            )),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Empty sync message",
+            )),
+        }
+    }
+}
+
 #[async_trait]
 impl request_response::Codec for SyncCodec {
     type Protocol = SyncProtocol;
@@ -102,23 +197,100 @@ impl request_response::Codec for SyncCodec {
     where
         T: AsyncRead + Unpin + Send,
     {
-        Ok(SyncRequest(read_length_prefixed(io, 8).await?))
+        Self::decode(&read_length_prefixed(io, MAX_REQUEST_SIZE).await?)
     }
 
     async fn read_response<T>(&mut self, _: &SyncProtocol, io: &mut T) -> io::Result<Self::Response>
     where
         T: AsyncRead + Unpin + Send,
     {
-        Ok(SyncResponse(
+        Self::decode(&read_length_prefixed(io, MAX_TRANSMIT_SIZE).await?)
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &SyncProtocol,
+        io: &mut T,
+        request: SyncRequest,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_length_prefixed(io, Self::encode(&request)?).await?;
+        io.close().await?;
+        Ok(())
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &SyncProtocol,
+        io: &mut T,
+        response: SyncResponse,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_length_prefixed(io, Self::encode(&response)?).await?;
+        io.close().await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactBlockRequest(pub Vec<u8>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactBlockResponse(pub Vec<u8>);
+
+#[derive(Debug, Clone)]
+pub struct CompactBlockProtocol();
+
+impl ProtocolName for CompactBlockProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        "/compact-block/1".as_bytes()
+    }
+}
+
+#[derive(Clone)]
+pub struct CompactBlockCodec();
+
+#[async_trait]
+impl request_response::Codec for CompactBlockCodec {
+    type Protocol = CompactBlockProtocol;
+    type Request = CompactBlockRequest;
+    type Response = CompactBlockResponse;
+
+    async fn read_request<T>(
+        &mut self,
+        _: &CompactBlockProtocol,
+        io: &mut T,
+    ) -> io::Result<Self::Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        Ok(CompactBlockRequest(
+            read_length_prefixed(io, MAX_REQUEST_SIZE).await?,
+        ))
+    }
+
+    async fn read_response<T>(
+        &mut self,
+        _: &CompactBlockProtocol,
+        io: &mut T,
+    ) -> io::Result<Self::Response>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        Ok(CompactBlockResponse(
             read_length_prefixed(io, MAX_TRANSMIT_SIZE).await?,
         ))
     }
 
     async fn write_request<T>(
         &mut self,
-        _: &SyncProtocol,
+        _: &CompactBlockProtocol,
         io: &mut T,
-        SyncRequest(data): SyncRequest,
+        CompactBlockRequest(data): CompactBlockRequest,
     ) -> io::Result<()>
     where
         T: AsyncWrite + Unpin + Send,
@@ -130,9 +302,74 @@ impl request_response::Codec for SyncCodec {
 
     async fn write_response<T>(
         &mut self,
-        _: &SyncProtocol,
+        _: &CompactBlockProtocol,
+        io: &mut T,
+        CompactBlockResponse(data): CompactBlockResponse,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_length_prefixed(io, data).await?;
+        io.close().await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionRequest(pub Vec<u8>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionResponse(pub Vec<u8>);
+
+#[derive(Debug, Clone)]
+pub struct TransactionProtocol();
+
+impl ProtocolName for TransactionProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        "/transaction/1".as_bytes()
+    }
+}
+
+#[derive(Clone)]
+pub struct TransactionCodec();
+
+#[async_trait]
+impl request_response::Codec for TransactionCodec {
+    type Protocol = TransactionProtocol;
+    type Request = TransactionRequest;
+    type Response = TransactionResponse;
+
+    async fn read_request<T>(
+        &mut self,
+        _: &TransactionProtocol,
+        io: &mut T,
+    ) -> io::Result<Self::Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        Ok(TransactionRequest(
+            read_length_prefixed(io, MAX_REQUEST_SIZE).await?,
+        ))
+    }
+
+    async fn read_response<T>(
+        &mut self,
+        _: &TransactionProtocol,
         io: &mut T,
-        SyncResponse(data): SyncResponse,
+    ) -> io::Result<Self::Response>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        Ok(TransactionResponse(
+            read_length_prefixed(io, MAX_TRANSMIT_SIZE).await?,
+        ))
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &TransactionProtocol,
+        io: &mut T,
+        TransactionRequest(data): TransactionRequest,
     ) -> io::Result<()>
     where
         T: AsyncWrite + Unpin + Send,
@@ -141,4 +378,62 @@ impl request_response::Codec for SyncCodec {
         io.close().await?;
         Ok(())
     }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &TransactionProtocol,
+        io: &mut T,
+        TransactionResponse(data): TransactionResponse,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_length_prefixed(io, data).await?;
+        io.close().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::request_response::Codec;
+
+    #[test]
+    fn sync_codec_version() {
+        let request = SyncRequest::GetBlockRange { start: 1, end: 10 };
+        let mut bytes = SyncCodec::encode(&request).unwrap();
+
+        assert_eq!(bytes[0], SYNC_PROTOCOL_VERSION);
+        assert!(matches!(
+            SyncCodec::decode::<SyncRequest>(&bytes).unwrap(),
+            SyncRequest::GetBlockRange { start: 1, end: 10 }
+        ));
+
+        bytes[0] = SYNC_PROTOCOL_VERSION + 1;
+        assert!(SyncCodec::decode::<SyncRequest>(&bytes).is_err());
+        assert!(SyncCodec::decode::<SyncRequest>(&[]).is_err());
+    }
+
+    #[test]
+    fn request_size_limit() {
+        async_std::task::block_on(async {
+            for size in [MAX_REQUEST_SIZE, MAX_REQUEST_SIZE + 1] {
+                let mut bytes = vec![];
+                write_length_prefixed(&mut bytes, vec![0; size])
+                    .await
+                    .unwrap();
+
+                let compact_block = CompactBlockCodec()
+                    .read_request(&CompactBlockProtocol(), &mut io::Cursor::new(&bytes))
+                    .await;
+                let transaction = TransactionCodec()
+                    .read_request(&TransactionProtocol(), &mut io::Cursor::new(&bytes))
+                    .await;
+
+                assert_eq!(compact_block.is_ok(), size <= MAX_REQUEST_SIZE);
+                assert_eq!(transaction.is_ok(), size <= MAX_REQUEST_SIZE);
+            }
+        });
+    }
 }
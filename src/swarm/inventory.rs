@@ -0,0 +1,82 @@
+use crate::{constants::*, primitive::*};
+use libp2p::PeerId;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Transaction hashes known to each connected peer
+#[derive(Default)]
+pub struct KnownInventory {
+    peers: HashMap<PeerId, KnownSet>,
+}
+
+impl KnownInventory {
+    /// Marking hashes as known to the peer
+    pub fn insert(&mut self, peer: &PeerId, hashes: &[Hash]) {
+        let known = self.peers.entry(*peer).or_default();
+
+        for hash in hashes {
+            known.insert(*hash);
+        }
+    }
+
+    /// Getting hashes not yet known to the peer and marking them as known
+    pub fn filter_unknown(&mut self, peer: &PeerId, hashes: &[Hash]) -> Vec<Hash> {
+        let known = self.peers.entry(*peer).or_default();
+
+        hashes
+            .iter()
+            .filter(|hash| known.insert(**hash))
+            .cloned()
+            .collect()
+    }
+
+    /// Removing a disconnected peer
+    pub fn remove_peer(&mut self, peer: &PeerId) {
+        self.peers.remove(peer);
+    }
+}
+
+/// Set of hashes bounded by `MAX_KNOWN_INVENTORY`, the oldest hashes are evicted first
+#[derive(Default)]
+struct KnownSet {
+    hashes: HashSet<Hash>,
+    order: VecDeque<Hash>,
+}
+
+impl KnownSet {
+    /// Returns `true` if the hash was not known
+    fn insert(&mut self, hash: Hash) -> bool {
+        if !self.hashes.insert(hash) {
+            return false;
+        }
+
+        self.order.push_back(hash);
+
+        if self.order.len() > MAX_KNOWN_INVENTORY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_unknown() {
+        let mut inventory = KnownInventory::default();
+        let peer = PeerId::random();
+
+        inventory.insert(&peer, &[[1u8; 32]]);
+
+        let unknown = inventory.filter_unknown(&peer, &[[1u8; 32], [2u8; 32]]);
+        assert_eq!(unknown, vec![[2u8; 32]]);
+        assert!(inventory.filter_unknown(&peer, &[[2u8; 32]]).is_empty());
+
+        inventory.remove_peer(&peer);
+        assert_eq!(inventory.filter_unknown(&peer, &[[2u8; 32]]).len(), 1);
+    }
+}
@@ -0,0 +1,330 @@
+use crate::constants::*;
+use libp2p::{
+    core::Endpoint,
+    multiaddr::Protocol,
+    swarm::{
+        dummy, ConnectionClosed, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour,
+        PollParameters, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+    },
+    Multiaddr, PeerId,
+};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    io,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Instant,
+};
+
+/// Limits of incoming traffic and outgoing requests per peer
+pub struct Limits {
+    requests: HashMap<PeerId, TokenBucket>,
+    gossip: HashMap<PeerId, TokenBucket>,
+    outstanding: HashMap<PeerId, usize>,
+    metrics: Arc<Metrics>,
+}
+
+impl Limits {
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Self {
+            requests: HashMap::new(),
+            gossip: HashMap::new(),
+            outstanding: HashMap::new(),
+            metrics,
+        }
+    }
+
+    /// Checking the rate limit of incoming requests from the peer
+    pub fn allow_request(&mut self, peer: &PeerId) -> bool {
+        let allowed = self
+            .requests
+            .entry(*peer)
+            .or_insert_with(|| TokenBucket::new(REQUEST_RATE, REQUEST_BURST))
+            .try_take(Instant::now());
+
+        if !allowed {
+            self.metrics
+                .throttled_requests
+                .fetch_add(1, Ordering::Relaxed);
+        }
+
+        allowed
+    }
+
+    /// Checking the rate limit of gossip messages from the peer
+    pub fn allow_gossip(&mut self, peer: &PeerId) -> bool {
+        let allowed = self
+            .gossip
+            .entry(*peer)
+            .or_insert_with(|| TokenBucket::new(GOSSIP_RATE, GOSSIP_BURST))
+            .try_take(Instant::now());
+
+        if !allowed {
+            self.metrics
+                .throttled_gossip
+                .fetch_add(1, Ordering::Relaxed);
+        }
+
+        allowed
+    }
+
+    /// Registering an outgoing request, returns `false` if the peer has too many outstanding requests
+    pub fn start_request(&mut self, peer: &PeerId) -> bool {
+        let outstanding = self.outstanding.entry(*peer).or_default();
+
+        if *outstanding >= MAX_OUTSTANDING_REQUESTS {
+            self.metrics
+                .dropped_requests
+                .fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        *outstanding += 1;
+        true
+    }
+
+    /// Completing an outgoing request by response or failure
+    pub fn finish_request(&mut self, peer: &PeerId) {
+        if let Some(outstanding) = self.outstanding.get_mut(peer) {
+            *outstanding = outstanding.saturating_sub(1);
+        }
+    }
+
+    /// Removing a disconnected peer
+    pub fn remove_peer(&mut self, peer: &PeerId) {
+        self.requests.remove(peer);
+        self.gossip.remove(peer);
+        self.outstanding.remove(peer);
+    }
+}
+
+/// Behaviour denying incoming connections from IP addresses with too many connections,
+/// the existing connections of the address are kept
+pub struct ConnectionLimits {
+    connections: HashMap<IpAddr, usize>,
+    addresses: HashMap<ConnectionId, IpAddr>,
+    metrics: Arc<Metrics>,
+}
+
+impl ConnectionLimits {
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Self {
+            connections: HashMap::new(),
+            addresses: HashMap::new(),
+            metrics,
+        }
+    }
+
+    /// Checking the number of established connections of the address
+    fn check(&self, address: &Multiaddr) -> Result<Option<IpAddr>, ConnectionDenied> {
+        let ip = match ip_address(address) {
+            Some(ip) => ip,
+            None => return Ok(None),
+        };
+
+        if self.connections.get(&ip).copied().unwrap_or(0) >= MAX_CONNECTIONS_PER_IP {
+            self.metrics
+                .rejected_connections
+                .fetch_add(1, Ordering::Relaxed);
+            log::warn!("Too many connections from the address: {address:?}");
+
+            return Err(ConnectionDenied::new(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("Too many connections from {ip}"),
+            )));
+        }
+
+        Ok(Some(ip))
+    }
+}
+
+impl NetworkBehaviour for ConnectionLimits {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type OutEvent = Infallible;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.check(remote_addr).map(|_| ())
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        // Checking again, the pending connections of the address may be established meanwhile
+        if let Some(ip) = self.check(remote_addr)? {
+            *self.connections.entry(ip).or_default() += 1;
+            self.addresses.insert(connection_id, ip);
+        }
+
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm<Self::ConnectionHandler>) {
+        if let FromSwarm::ConnectionClosed(ConnectionClosed { connection_id, .. }) = event {
+            if let Some(ip) = self.addresses.remove(&connection_id) {
+                if let Some(connections) = self.connections.get_mut(&ip) {
+                    *connections = connections.saturating_sub(1);
+
+                    if *connections == 0 {
+                        self.connections.remove(&ip);
+                    }
+                }
+            }
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        match event {}
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut Context<'_>,
+        _: &mut impl PollParameters,
+    ) -> Poll<ToSwarm<Self::OutEvent, THandlerInEvent<Self>>> {
+        Poll::Pending
+    }
+}
+
+fn ip_address(address: &Multiaddr) -> Option<IpAddr> {
+    address.iter().find_map(|protocol| match protocol {
+        Protocol::Ip4(ip) => Some(IpAddr::V4(ip)),
+        Protocol::Ip6(ip) => Some(IpAddr::V6(ip)),
+        _ => None,
+    })
+}
+
+/// Counters of throttled network traffic
+#[derive(Default)]
+pub struct Metrics {
+    pub throttled_requests: AtomicU64,
+    pub throttled_gossip: AtomicU64,
+    pub rejected_connections: AtomicU64,
+    pub dropped_requests: AtomicU64,
+}
+
+struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, burst: f64) -> Self {
+        Self {
+            rate,
+            burst,
+            tokens: burst,
+            last: Instant::now(),
+        }
+    }
+
+    fn try_take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.last = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::core::ConnectedPoint;
+    use std::time::Duration;
+
+    #[test]
+    fn token_bucket() {
+        let mut bucket = TokenBucket::new(2.0, 3.0);
+        let now = bucket.last;
+
+        for _ in 0..3 {
+            assert!(bucket.try_take(now));
+        }
+        assert!(!bucket.try_take(now));
+
+        let now = now + Duration::from_millis(500);
+        assert!(bucket.try_take(now));
+        assert!(!bucket.try_take(now));
+    }
+
+    #[test]
+    fn connections_per_ip() {
+        let mut limits = ConnectionLimits::new(Arc::default());
+        let local: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+        let address: Multiaddr = "/ip4/10.0.0.1/tcp/4001".parse().unwrap();
+        let peer = PeerId::random();
+
+        for id in 0..MAX_CONNECTIONS_PER_IP {
+            let connection_id = ConnectionId::new_unchecked(id);
+            assert!(limits
+                .handle_pending_inbound_connection(connection_id, &local, &address)
+                .is_ok());
+            assert!(limits
+                .handle_established_inbound_connection(connection_id, peer, &local, &address)
+                .is_ok());
+        }
+
+        let extra = ConnectionId::new_unchecked(MAX_CONNECTIONS_PER_IP);
+        assert!(limits
+            .handle_pending_inbound_connection(extra, &local, &address)
+            .is_err());
+
+        // Another address is not affected
+        let other: Multiaddr = "/ip4/10.0.0.2/tcp/4001".parse().unwrap();
+        assert!(limits
+            .handle_pending_inbound_connection(extra, &local, &other)
+            .is_ok());
+
+        let endpoint = ConnectedPoint::Listener {
+            local_addr: local.clone(),
+            send_back_addr: address.clone(),
+        };
+        limits.on_swarm_event(FromSwarm::ConnectionClosed(ConnectionClosed {
+            peer_id: peer,
+            connection_id: ConnectionId::new_unchecked(0),
+            endpoint: &endpoint,
+            handler: dummy::ConnectionHandler,
+            remaining_established: 0,
+        }));
+        assert!(limits
+            .handle_pending_inbound_connection(extra, &local, &address)
+            .is_ok());
+    }
+}
@@ -1,14 +1,22 @@
 pub mod behaviour;
+pub mod inventory;
+pub mod limits;
 
 use crate::constants::*;
+use async_std::sync::Arc;
 use behaviour::Behaviour;
+#[cfg(feature = "quic")]
+use libp2p::{core::muxing::StreamMuxerBox, futures::future::Either};
 use libp2p::{
     core::transport::upgrade::Version, gossipsub, identity, noise, swarm, tcp, yamux, PeerId,
     Transport,
 };
+#[cfg(feature = "quic")]
+use libp2p_quic as quic;
+use limits::Metrics;
 use std::{error::Error, time::Duration};
 
-pub async fn init() -> Result<swarm::Swarm<Behaviour>, Box<dyn Error>> {
+pub async fn init(metrics: Arc<Metrics>) -> Result<swarm::Swarm<Behaviour>, Box<dyn Error>> {
     let local_key = identity::Keypair::generate_ed25519();
     let local_peer_id = PeerId::from(local_key.public());
 
@@ -19,14 +27,23 @@ pub async fn init() -> Result<swarm::Swarm<Behaviour>, Box<dyn Error>> {
         .timeout(Duration::from_secs(20))
         .boxed();
 
-    let mut behaviour = Behaviour::new(local_key, local_peer_id).await?;
+    // QUIC provides its own encryption and multiplexing, so only the muxer types are unified
+    #[cfg(feature = "quic")]
+    let transport = transport
+        .or_transport(quic::async_std::Transport::new(quic::Config::new(
+            &local_key,
+        )))
+        .map(|output, _| match output {
+            Either::Left((peer_id, muxer)) => (peer_id, muxer),
+            Either::Right((peer_id, muxer)) => (peer_id, StreamMuxerBox::new(muxer)),
+        })
+        .boxed();
+
+    let mut behaviour = Behaviour::new(local_key, local_peer_id, metrics).await?;
 
     behaviour
         .gossipsub
         .subscribe(&gossipsub::IdentTopic::new(BLOCK_TOPIC))?;
-    behaviour
-        .gossipsub
-        .subscribe(&gossipsub::IdentTopic::new(TRANSACTION_TOPIC))?;
 
     Ok(swarm::SwarmBuilder::with_async_std_executor(transport, behaviour, local_peer_id).build())
 }
@@ -34,3 +51,60 @@ pub async fn init() -> Result<swarm::Swarm<Behaviour>, Box<dyn Error>> {
 pub fn protocol_version() -> String {
     format!("gem/{}", CARGO_PKG_VERSION)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::{future, task};
+    use libp2p::{futures::StreamExt, multiaddr::Protocol, swarm::SwarmEvent, Multiaddr};
+
+    /// Connecting two nodes on the loopback address of the transport, returns the address
+    /// of the connection established by the dialer
+    fn connect(listen_address: &str) -> Multiaddr {
+        task::block_on(async {
+            let mut listener = init(Arc::default()).await.unwrap();
+            let mut dialer = init(Arc::default()).await.unwrap();
+
+            listener.listen_on(listen_address.parse().unwrap()).unwrap();
+            let address = loop {
+                if let SwarmEvent::NewListenAddr { address, .. } = listener.select_next_some().await
+                {
+                    break address;
+                }
+            };
+            task::spawn(async move {
+                loop {
+                    listener.select_next_some().await;
+                }
+            });
+
+            dialer.dial(address).unwrap();
+            future::timeout(Duration::from_secs(10), async {
+                loop {
+                    if let SwarmEvent::ConnectionEstablished { endpoint, .. } =
+                        dialer.select_next_some().await
+                    {
+                        break endpoint.get_remote_address().clone();
+                    }
+                }
+            })
+            .await
+            .unwrap()
+        })
+    }
+
+    #[test]
+    fn connect_tcp_loopback() {
+        let address = connect("/ip4/127.0.0.1/tcp/0");
+        assert!(address
+            .iter()
+            .any(|protocol| matches!(protocol, Protocol::Tcp(_))));
+    }
+
+    #[cfg(feature = "quic")]
+    #[test]
+    fn connect_quic_loopback() {
+        let address = connect("/ip4/127.0.0.1/udp/0/quic-v1");
+        assert!(address.iter().any(|protocol| protocol == Protocol::QuicV1));
+    }
+}
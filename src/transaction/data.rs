@@ -2,7 +2,7 @@ use crate::primitive::*;
 use serde::{Deserialize, Serialize};
 
 /// Data specific to a particular transaction type
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum Data {
     RotatePublicKey {
         public_key: PublicKey,
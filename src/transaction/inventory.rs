@@ -0,0 +1,18 @@
+use crate::{primitive::*, transaction::Transaction};
+use serde::{Deserialize, Serialize};
+
+/// Transaction relay request
+#[derive(Serialize, Deserialize, Debug)]
+pub enum InventoryRequest {
+    /// Announcing hashes of transactions available to the peer
+    Announce(Vec<Hash>),
+    /// Requesting transactions by hashes
+    Get(Vec<Hash>),
+}
+
+/// Transaction relay response
+#[derive(Serialize, Deserialize, Debug)]
+pub enum InventoryResponse {
+    Ack,
+    Transactions(Vec<Transaction>),
+}
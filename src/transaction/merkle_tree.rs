@@ -93,7 +93,16 @@ mod tests {
             public_key: EMPTY_PUBLIC_KEY,
         };
 
-        let mut transaction = Transaction::new(EMPTY_ADDRESS, public_key, 0, 1024, 0, data);
+        let mut transaction = Transaction::new(
+            0,
+            EMPTY_ADDRESS,
+            public_key,
+            0,
+            1024,
+            0,
+            TRANSACTION_LIFETIME_BLOCKS,
+            data,
+        );
         transaction.sign(&secret_key).unwrap();
 
         let mut transactions = Transactions::default();
@@ -1,9 +1,11 @@
 mod data;
+mod inventory;
 mod merkle_tree;
 mod transaction;
 mod transactions;
 
 pub use data::Data;
+pub use inventory::{InventoryRequest, InventoryResponse};
 pub use merkle_tree::MerkleTree;
 pub use transaction::Transaction;
 pub use transactions::Transactions;
@@ -4,33 +4,41 @@ use serde::{Deserialize, Serialize};
 use serde_big_array::BigArray;
 
 /// Transaction data. Data specific to a particular transaction type are stored in the `data` field
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Transaction {
+    pub chain_id: ChainId,
     pub sender: Address,
     pub sender_public_key: PublicKey,
     pub sequence_number: u64,
     pub fee: u64,
     pub timestamp: u128,
+    /// Height of the last block that can include the transaction
+    pub valid_until_height: u64,
     pub data: Data,
     #[serde(with = "BigArray")]
     pub signature: Signature,
 }
 
 impl Transaction {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        chain_id: ChainId,
         sender: Address,
         sender_public_key: PublicKey,
         sequence_number: u64,
         fee: u64,
         timestamp: u128,
+        valid_until_height: u64,
         data: Data,
     ) -> Self {
         Self {
+            chain_id,
             sender,
             sender_public_key,
             sequence_number,
             fee,
             timestamp,
+            valid_until_height,
             data,
             signature: EMPTY_SIGNATURE,
         }
@@ -41,6 +49,30 @@ impl Transaction {
         self.data.type_id()
     }
 
+    /// Checking that the transaction can be included in a block with the height and the timestamp
+    pub fn check_expiry(&self, height: u64, timestamp: u128) -> Result<()> {
+        if self.timestamp > timestamp.saturating_add(MAX_TRANSACTION_FUTURE) {
+            Err(anyhow!(
+                "Transaction timestamp is in the future: {}",
+                self.timestamp
+            ))
+        } else if self.is_expired(height, timestamp) {
+            Err(anyhow!(
+                "Transaction is expired: valid until height {} and timestamp {}",
+                self.valid_until_height,
+                self.timestamp.saturating_add(TRANSACTION_LIFETIME)
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The transaction can no longer be included in a block with the height and the timestamp
+    pub fn is_expired(&self, height: u64, timestamp: u128) -> bool {
+        height > self.valid_until_height
+            || self.timestamp.saturating_add(TRANSACTION_LIFETIME) < timestamp
+    }
+
     /// Getting the amount depending on the type of transaction
     pub fn amount(&self) -> u64 {
         match self.data {
@@ -66,11 +98,14 @@ impl Cryptography for Transaction {
     fn as_data_for_signing(&self) -> Result<Vec<u8>> {
         let mut bytes: Vec<u8> = vec![];
 
-        bytes.extend_from_slice(&self.sender);
+        bytes.extend_from_slice(TRANSACTION_SIGNING_TAG);
+        bytes.extend_from_slice(&self.chain_id.to_le_bytes());
+        bytes.extend_from_slice(self.sender.as_ref());
         bytes.extend_from_slice(&self.sender_public_key);
         bytes.extend_from_slice(&self.sequence_number.to_le_bytes());
         bytes.extend_from_slice(&self.fee.to_le_bytes());
         bytes.extend_from_slice(&self.timestamp.to_le_bytes());
+        bytes.extend_from_slice(&self.valid_until_height.to_le_bytes());
 
         let data = bincode::serialize(&self.data)
             .map_err(|error| anyhow!("Failed to serialize data: {error:?}"))?;
@@ -85,6 +120,10 @@ impl Validation for Transaction {
     fn is_valid(&self, state: &State) -> Result<()> {
         self.signature_verify()?;
 
+        if state.chain_id() != self.chain_id {
+            return Err(anyhow!("Transaction of another chain: {}", self.chain_id));
+        }
+
         let sender = state
             .database
             .get_account_from_public_key(self.sender_public_key)?;
@@ -93,9 +132,12 @@ impl Validation for Transaction {
             Err(anyhow!(
                 "Received address does not match the sender in the transaction: {sender:?}"
             ))
-        } else if sender.sequence_number() + 1 == self.sequence_number {
+        } else if sender.sequence_number() + 1 != self.sequence_number {
+            // Only the number following the last number of the sender is accepted
             Err(anyhow!(
-                "Sequence number does not match the sender number: {sender:?}"
+                "Sequence number must be {}, got {}: {sender:?}",
+                sender.sequence_number() + 1,
+                self.sequence_number
             ))
         } else if sender.balance < self.amount() + self.fee {
             Err(anyhow!(
@@ -115,7 +157,7 @@ impl Validation for Transaction {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{account::Account, primitive::Network, wallet};
+    use crate::{account::Account, block::genesis, primitive::Network, wallet};
 
     #[test]
     fn signature_verify() {
@@ -126,18 +168,151 @@ mod tests {
             public_key: EMPTY_PUBLIC_KEY,
         };
 
-        let mut transaction = Transaction::new(account.address, public_key, 0, 1024, 0, data);
+        let mut transaction = Transaction::new(
+            0,
+            account.address,
+            public_key,
+            0,
+            1024,
+            0,
+            TRANSACTION_LIFETIME_BLOCKS,
+            data,
+        );
         transaction.sign(&secret_key).unwrap();
 
         assert!(transaction.signature_verify().is_ok());
     }
 
+    #[test]
+    fn replay_across_networks() {
+        let (secret_key, public_key) = wallet::generate();
+        let account = Account::from_public_key(public_key, Network::Testnet);
+
+        let testnet = genesis::chain_id(Network::Testnet).unwrap();
+        let mainnet = genesis::chain_id(Network::Mainnet).unwrap();
+        assert_ne!(testnet, mainnet);
+
+        let data = Data::RotatePublicKey {
+            public_key: EMPTY_PUBLIC_KEY,
+        };
+        let mut transaction = Transaction::new(
+            testnet,
+            account.address,
+            public_key,
+            0,
+            1024,
+            0,
+            TRANSACTION_LIFETIME_BLOCKS,
+            data,
+        );
+        transaction.sign(&secret_key).unwrap();
+
+        // The signature of the testnet transaction does not cover the mainnet chain id
+        transaction.chain_id = mainnet;
+        assert!(transaction.signature_verify().is_err());
+    }
+
+    #[test]
+    fn sequence_number() {
+        let state =
+            State::from_database(crate::state::Database::memory().unwrap(), Network::Testnet)
+                .unwrap();
+
+        let (secret_key, public_key) = wallet::generate();
+        let mut account = Account::from_public_key(public_key, Network::Testnet);
+        account.balance = MIN_FEE;
+        account.inc_sequence_number();
+
+        let mut batch = state.database.create_batch();
+        state.database.put_account(&mut batch, &account).unwrap();
+        state.database.write(batch).unwrap();
+
+        // Only the number following the sender sequence number is accepted
+        for (sequence_number, valid) in [(1, false), (2, true), (3, false)] {
+            let data = Data::RotatePublicKey {
+                public_key: EMPTY_PUBLIC_KEY,
+            };
+            let mut transaction = Transaction::new(
+                state.chain_id(),
+                account.address,
+                public_key,
+                sequence_number,
+                MIN_FEE,
+                0,
+                TRANSACTION_LIFETIME_BLOCKS,
+                data,
+            );
+            transaction.sign(&secret_key).unwrap();
+
+            assert_eq!(transaction.is_valid(&state).is_ok(), valid);
+        }
+    }
+
+    #[test]
+    fn validity_window() {
+        let data = Data::RotatePublicKey {
+            public_key: EMPTY_PUBLIC_KEY,
+        };
+        let transaction = Transaction::new(
+            0,
+            EMPTY_ADDRESS,
+            EMPTY_PUBLIC_KEY,
+            0,
+            1024,
+            1_000_000,
+            10,
+            data,
+        );
+
+        assert!(transaction.check_expiry(1, 1_000_000).is_ok());
+        assert!(transaction
+            .check_expiry(10, 1_000_000 + TRANSACTION_LIFETIME)
+            .is_ok());
+        assert!(transaction
+            .check_expiry(1, 1_000_001 + TRANSACTION_LIFETIME)
+            .is_err());
+        assert!(transaction.is_expired(1, 1_000_001 + TRANSACTION_LIFETIME));
+
+        // The expiry height is the last height that can include the transaction
+        assert!(transaction.check_expiry(11, 1_000_000).is_err());
+        assert!(transaction.is_expired(11, 1_000_000));
+
+        assert!(transaction
+            .check_expiry(1, 1_000_000 - MAX_TRANSACTION_FUTURE)
+            .is_ok());
+        assert!(transaction
+            .check_expiry(1, 999_999 - MAX_TRANSACTION_FUTURE)
+            .is_err());
+    }
+
+    #[test]
+    fn expiry_height_is_signed() {
+        let (secret_key, public_key) = wallet::generate();
+        let data = Data::RotatePublicKey {
+            public_key: EMPTY_PUBLIC_KEY,
+        };
+        let mut transaction = Transaction::new(0, EMPTY_ADDRESS, public_key, 0, 1024, 0, 10, data);
+        transaction.sign(&secret_key).unwrap();
+
+        transaction.valid_until_height = 11;
+        assert!(transaction.signature_verify().is_err());
+    }
+
     #[test]
     fn rotate_public_key() {
         let data = Data::RotatePublicKey {
             public_key: EMPTY_PUBLIC_KEY,
         };
-        let transaction = Transaction::new(EMPTY_ADDRESS, EMPTY_PUBLIC_KEY, 0, 1024, 0, data);
+        let transaction = Transaction::new(
+            0,
+            EMPTY_ADDRESS,
+            EMPTY_PUBLIC_KEY,
+            0,
+            1024,
+            0,
+            TRANSACTION_LIFETIME_BLOCKS,
+            data,
+        );
 
         assert_eq!(transaction.type_id(), 1);
         assert_eq!(transaction.amount(), 0);
@@ -153,7 +328,16 @@ mod tests {
             amount: 1024,
             attachment: String::from("test"),
         };
-        let transaction = Transaction::new(EMPTY_ADDRESS, EMPTY_PUBLIC_KEY, 0, 1024, 0, data);
+        let transaction = Transaction::new(
+            0,
+            EMPTY_ADDRESS,
+            EMPTY_PUBLIC_KEY,
+            0,
+            1024,
+            0,
+            TRANSACTION_LIFETIME_BLOCKS,
+            data,
+        );
 
         assert_eq!(transaction.type_id(), 2);
         assert_eq!(transaction.amount(), 1024);
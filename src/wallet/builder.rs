@@ -0,0 +1,135 @@
+use crate::{
+    constants::*,
+    primitive::*,
+    rpc::client::RpcClient,
+    transaction::{Data, Transaction},
+};
+use anyhow::{anyhow, Result};
+use base58::{FromBase58, ToBase58};
+use std::{fs, time::SystemTime};
+
+/// Decoding the base58 public key
+pub fn parse_public_key(base58: &str) -> Result<PublicKey> {
+    let bytes = base58
+        .from_base58()
+        .map_err(|error| anyhow!("Base58 decode failed: {error:?}"))?;
+
+    PublicKey::try_from(bytes.as_slice())
+        .map_err(|_| anyhow!("Invalid public key length: {}", bytes.len()))
+}
+
+/// Building the unsigned transaction of the sender with the next sequence number known to the node
+pub fn build_transaction(
+    client: &RpcClient,
+    chain_id: ChainId,
+    sender: Address,
+    sender_public_key: PublicKey,
+    fee: u64,
+    data: Data,
+) -> Result<Transaction> {
+    let account = client.get_account(&sender.to_string())?;
+
+    // The next sequence number is taken until the pending transaction is mined or expires
+    if account.pending_transactions > 0 {
+        return Err(anyhow!(
+            "The sender has a pending transaction in the mempool of the node, wait until it is included in a block or expires"
+        ));
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_millis();
+
+    Ok(Transaction::new(
+        chain_id,
+        sender,
+        sender_public_key,
+        account.sequence_number + 1,
+        fee,
+        timestamp,
+        account.height + TRANSACTION_LIFETIME_BLOCKS,
+        data,
+    ))
+}
+
+/// Saving the transaction file of the offline signing, the hex encoded bincode transaction
+/// as accepted by `gem_sendTransaction`, the signature is empty until it is signed
+pub fn save_transaction(path: &str, transaction: &Transaction) -> Result<()> {
+    fs::write(path, hex::encode(bincode::serialize(transaction)?))?;
+    Ok(())
+}
+
+pub fn load_transaction(path: &str) -> Result<Transaction> {
+    let bytes = hex::decode(fs::read_to_string(path)?.trim())?;
+    Ok(bincode::deserialize(&bytes)?)
+}
+
+/// Description of the transaction to check it before signing
+pub fn describe(transaction: &Transaction) -> String {
+    let action = match &transaction.data {
+        Data::Transfer {
+            recipient,
+            amount,
+            attachment,
+        } => format!("Transfer {amount} to {recipient} with attachment {attachment:?}"),
+        Data::RotatePublicKey { public_key } => {
+            format!("Rotate the public key to {}", public_key.to_base58())
+        }
+    };
+
+    format!(
+        "{action}\nChain id: {}\nSender: {}\nSender public key: {}\nSequence number: {}\nFee: {}\nValid until height: {}\nSigned: {}",
+        transaction.chain_id,
+        transaction.sender,
+        transaction.sender_public_key.to_base58(),
+        transaction.sequence_number,
+        transaction.fee,
+        transaction.valid_until_height,
+        transaction.signature != EMPTY_SIGNATURE,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{account::Account, wallet};
+
+    #[test]
+    fn offline_signing() {
+        let path = std::env::temp_dir().join(format!("gem-transaction-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let (secret_key, public_key) = wallet::generate();
+        let sender = Account::from_public_key(public_key, Network::Testnet).address;
+        let data = Data::Transfer {
+            recipient: sender,
+            amount: 10,
+            attachment: String::new(),
+        };
+
+        // The watch-only side saves the transaction without the secret key
+        let transaction = Transaction::new(
+            0,
+            sender,
+            public_key,
+            1,
+            MIN_FEE,
+            0,
+            TRANSACTION_LIFETIME_BLOCKS,
+            data,
+        );
+        save_transaction(path, &transaction).unwrap();
+
+        let mut unsigned = load_transaction(path).unwrap();
+        assert!(unsigned.signature_verify().is_err());
+        assert!(describe(&unsigned).ends_with("Signed: false"));
+
+        unsigned.sign(&secret_key).unwrap();
+        save_transaction(path, &unsigned).unwrap();
+
+        let signed = load_transaction(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert!(signed.signature_verify().is_ok());
+        assert_eq!(signed.hash().unwrap(), transaction.hash().unwrap());
+    }
+}
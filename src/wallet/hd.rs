@@ -0,0 +1,93 @@
+use crate::{constants::*, primitive::*};
+use anyhow::{anyhow, Result};
+use bip39::Mnemonic;
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+
+type HmacSha512 = Hmac<Sha512>;
+
+/// Offset of the hardened indices, ed25519 derivation supports only hardened children
+const HARDENED: u32 = 0x8000_0000;
+
+/// Generation of a 24 words mnemonic phrase
+pub fn generate_mnemonic() -> Result<Mnemonic> {
+    let entropy: [u8; 32] = rand::random();
+
+    Mnemonic::from_entropy(&entropy).map_err(|error| anyhow!("Mnemonic generation failed: {error}"))
+}
+
+/// Parsing and validating the checksum of the mnemonic phrase
+pub fn parse_mnemonic(phrase: &str) -> Result<Mnemonic> {
+    Mnemonic::parse(phrase).map_err(|error| anyhow!("Invalid mnemonic phrase: {error}"))
+}
+
+/// Derivation path of the account `m/44'/coin_type'/index'`
+pub fn account_path(index: u32) -> [u32; 3] {
+    [44, HD_COIN_TYPE, index]
+}
+
+/// SLIP-0010 derivation of the ed25519 secret key from the seed, all indices are hardened
+pub fn derive(seed: &[u8], path: &[u32]) -> Result<SecretKey> {
+    let (mut key, mut chain_code) = split(hmac(b"ed25519 seed", &[seed])?);
+
+    for index in path {
+        if *index >= HARDENED {
+            return Err(anyhow!("Derivation index is out of range: {index}"));
+        }
+        let index = (index | HARDENED).to_be_bytes();
+
+        (key, chain_code) = split(hmac(&chain_code, &[&[0], &key, &index])?);
+    }
+
+    Ok(key)
+}
+
+fn hmac(key: &[u8], data: &[&[u8]]) -> Result<[u8; 64]> {
+    let mut mac = HmacSha512::new_from_slice(key)?;
+    for data in data {
+        mac.update(data);
+    }
+
+    Ok(mac.finalize().into_bytes().into())
+}
+
+fn split(bytes: [u8; 64]) -> (SecretKey, [u8; 32]) {
+    let mut key = [0; 32];
+    let mut chain_code = [0; 32];
+    key.copy_from_slice(&bytes[..32]);
+    chain_code.copy_from_slice(&bytes[32..]);
+
+    (key, chain_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slip10_test_vector() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+
+        assert_eq!(
+            hex::encode(derive(&seed, &[]).unwrap()),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        assert_eq!(
+            hex::encode(derive(&seed, &[0]).unwrap()),
+            "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"
+        );
+        assert_eq!(
+            hex::encode(derive(&seed, &[0, 1]).unwrap()),
+            "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2"
+        );
+    }
+
+    #[test]
+    fn mnemonic_recovery() {
+        let mnemonic = generate_mnemonic().unwrap();
+        let recovered = parse_mnemonic(&mnemonic.to_string()).unwrap();
+
+        assert_eq!(mnemonic.to_seed(""), recovered.to_seed(""));
+        assert!(parse_mnemonic("abandon abandon abandon").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use super::{AccountKey, KdfParams, Wallet};
+use crate::{constants::*, primitive::*};
+use anyhow::{anyhow, Result};
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit};
+use serde::Deserialize;
+
+/// Argon2 parameters of the unversioned wallet files
+const LEGACY_KDF: (u32, u32, u32) = (1024, 1, 1);
+
+/// Secret contents of the unversioned HD wallet
+#[derive(Deserialize)]
+enum Keystore {
+    Single(SecretKey),
+    Hd { seed: Vec<u8>, indices: Vec<u32> },
+}
+
+/// Decrypting the unversioned wallet: the hex encoded salt, nonce and ciphertext of
+/// either the bare secret key or the HD keystore
+pub fn load(text: &str, password: &str) -> Result<Wallet> {
+    let bytes = hex::decode(text.trim())?;
+    if bytes.len() <= 44 {
+        return Err(anyhow!("Wallet file is too short"));
+    }
+
+    let mut salt = [0u8; 32];
+    salt.copy_from_slice(&bytes[0..32]);
+    let mut nonce = [0u8; 12];
+    nonce.copy_from_slice(&bytes[32..44]);
+
+    let (m_cost, t_cost, p_cost) = LEGACY_KDF;
+    let kdf = KdfParams {
+        m_cost,
+        t_cost,
+        p_cost,
+        salt,
+    };
+    let cipher = ChaCha20Poly1305::new_from_slice(&kdf.derive_key(password)?)?;
+
+    let plaintext = cipher
+        .decrypt(&nonce.into(), &bytes[44..])
+        .map_err(|error| anyhow!("ChaCha20Poly1305 decrypt failed: {error:?}"))?;
+
+    // The first wallet files hold only the secret key
+    let keystore = if plaintext.len() == 32 {
+        let mut secret_key = EMPTY_SECRET_KEY;
+        secret_key.copy_from_slice(&plaintext);
+        Keystore::Single(secret_key)
+    } else {
+        bincode::deserialize(&plaintext)?
+    };
+
+    let mut wallet = Wallet::default();
+    match keystore {
+        Keystore::Single(secret_key) => {
+            wallet.push(AccountKey::Imported(secret_key), "", 0);
+        }
+        Keystore::Hd { seed, indices } => {
+            wallet.seed = Some(seed);
+            for index in indices {
+                wallet.push(AccountKey::Derived(index), "", 0);
+            }
+        }
+    }
+
+    Ok(wallet)
+}
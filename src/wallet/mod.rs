@@ -1,14 +1,231 @@
+pub mod builder;
+pub mod hd;
+mod legacy;
+mod unlock;
+
+pub use unlock::{MiningKey, PasswordSource};
+
 use crate::primitive::*;
 use anyhow::{anyhow, Result};
 use argon2::{Algorithm, Argon2, ParamsBuilder, Version};
 use base58::{FromBase58, ToBase58};
-use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit};
+use bip39::Mnemonic;
+use chacha20poly1305::{
+    aead::{Aead, Payload},
+    ChaCha20Poly1305, KeyInit,
+};
 use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
 use std::{
-    fs::File,
-    io::{Read, Write},
+    fs::{self, OpenOptions},
+    io::Write,
 };
 
+/// Magic bytes of the versioned wallet file
+const WALLET_MAGIC: &[u8; 4] = b"GEMW";
+/// Current version of the wallet file
+const WALLET_VERSION: u8 = 1;
+/// Upper bound of the Argon2 memory cost in KiB (4 GiB) accepted from wallet files
+const MAX_KDF_MEMORY: u32 = 4 * 1024 * 1024;
+
+/// Argon2id parameters recorded in the wallet header
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+    pub salt: [u8; 32],
+}
+
+/// Default parameters of new wallets: 64 MiB of memory and 3 passes
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self::new(64 * 1024, 3, 1)
+    }
+}
+
+impl KdfParams {
+    /// Parameters with a new random salt, the memory cost is in KiB
+    pub fn new(m_cost: u32, t_cost: u32, p_cost: u32) -> Self {
+        Self {
+            m_cost,
+            t_cost,
+            p_cost,
+            salt: rand::random(),
+        }
+    }
+
+    /// Checking that Argon2 accepts the parameters
+    pub fn validate(&self) -> Result<()> {
+        self.argon2().map(|_| ())
+    }
+
+    /// Deriving the cipher key from the password
+    pub fn derive_key(&self, password: &str) -> Result<Hash> {
+        let mut bytes = [0; 32];
+        self.argon2()?
+            .hash_password_into(password.as_bytes(), &self.salt, &mut bytes)
+            .map_err(|error| anyhow!("Argon2 hashing failed: {error:?}"))?;
+        Ok(bytes)
+    }
+
+    fn argon2(&self) -> Result<Argon2<'static>> {
+        if self.m_cost > MAX_KDF_MEMORY {
+            return Err(anyhow!(
+                "Argon2 memory cost is too large: {} KiB",
+                self.m_cost
+            ));
+        }
+
+        let mut builder = ParamsBuilder::new();
+
+        builder.m_cost(self.m_cost);
+        builder.t_cost(self.t_cost);
+        builder.p_cost(self.p_cost);
+
+        let params = builder
+            .build()
+            .map_err(|error| anyhow!("Argon2 params build failed: {error:?}"))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+/// Unencrypted header of the wallet file, authenticated together with the ciphertext
+#[derive(Serialize, Deserialize)]
+struct Header {
+    kdf: KdfParams,
+    nonce: [u8; 12],
+}
+
+/// Secret key of the wallet account
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum AccountKey {
+    /// Index of the account derived from the seed
+    Derived(u32),
+    /// Imported secret key
+    Imported(SecretKey),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WalletAccount {
+    pub label: String,
+    /// Height of the last block when the account was created
+    pub creation_height: u64,
+    pub key: AccountKey,
+}
+
+/// Encrypted contents of wallet.dat
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Wallet {
+    /// Seed of the mnemonic phrase
+    seed: Option<Vec<u8>>,
+    accounts: Vec<WalletAccount>,
+    /// Key derivation parameters of the file, the salt is renewed on each save
+    #[serde(skip)]
+    kdf: KdfParams,
+}
+
+impl Wallet {
+    /// Creating the HD wallet with the first account
+    pub fn from_mnemonic(mnemonic: &Mnemonic, label: &str, height: u64) -> Self {
+        let mut wallet = Self {
+            seed: Some(mnemonic.to_seed("").to_vec()),
+            ..Self::default()
+        };
+        wallet.push(AccountKey::Derived(0), label, height);
+
+        wallet
+    }
+
+    pub fn accounts(&self) -> &[WalletAccount] {
+        &self.accounts
+    }
+
+    pub fn kdf(&self) -> KdfParams {
+        self.kdf
+    }
+
+    /// Changing the key derivation parameters used by the next save
+    pub fn set_kdf(&mut self, kdf: KdfParams) -> Result<()> {
+        kdf.validate()?;
+        self.kdf = kdf;
+        Ok(())
+    }
+
+    /// Keypair of the account by its position in the wallet
+    pub fn keypair(&self, index: usize) -> Result<(SecretKey, PublicKey)> {
+        let account = self
+            .accounts
+            .get(index)
+            .ok_or_else(|| anyhow!("Account {index} does not exist in the wallet"))?;
+
+        match account.key {
+            AccountKey::Derived(child) => {
+                let seed = self
+                    .seed
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Wallet has no seed to derive the account {index}"))?;
+                keypair(hd::derive(seed, &hd::account_path(child))?)
+            }
+            AccountKey::Imported(secret_key) => keypair(secret_key),
+        }
+    }
+
+    /// Finding the position of the account with the public key
+    pub fn find_account(&self, public_key: PublicKey) -> Result<Option<usize>> {
+        for index in 0..self.accounts.len() {
+            if self.keypair(index)?.1 == public_key {
+                return Ok(Some(index));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Deriving the account with the next child index
+    pub fn add_account(&mut self, label: &str, height: u64) -> Result<usize> {
+        if self.seed.is_none() {
+            return Err(anyhow!("Wallet has no seed to derive accounts"));
+        }
+
+        let child = self
+            .accounts
+            .iter()
+            .filter_map(|account| match account.key {
+                AccountKey::Derived(child) => Some(child + 1),
+                AccountKey::Imported(_) => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        Ok(self.push(AccountKey::Derived(child), label, height))
+    }
+
+    /// Adding the imported secret key
+    pub fn import_key(&mut self, secret_key: SecretKey, label: &str, height: u64) -> usize {
+        self.push(AccountKey::Imported(secret_key), label, height)
+    }
+
+    fn push(&mut self, key: AccountKey, label: &str, height: u64) -> usize {
+        let index = self.accounts.len();
+        let label = if label.is_empty() {
+            format!("Account {index}")
+        } else {
+            label.to_string()
+        };
+
+        self.accounts.push(WalletAccount {
+            label,
+            creation_height: height,
+            key,
+        });
+
+        index
+    }
+}
+
 /// Keypair and account generation
 pub fn generate() -> (SecretKey, PublicKey) {
     let keypair = generate_keypair();
@@ -34,81 +251,115 @@ pub fn import(base58: &str) -> Result<(SecretKey, PublicKey)> {
     Ok((secret_key.to_bytes(), public_key.to_bytes()))
 }
 
-/// Loading a secret key from wallet.dat
-pub fn load(wallet_path: &str) -> Result<(SecretKey, PublicKey)> {
-    let password = rpassword::prompt_password("Wallet password: ")?;
+/// Asking the wallet password in the terminal
+pub fn prompt_password() -> Result<String> {
+    Ok(rpassword::prompt_password("Wallet password: ")?)
+}
 
-    let mut file = File::open(wallet_path)?;
+/// Asking the new wallet password twice in the terminal
+pub fn prompt_new_password() -> Result<String> {
+    let password = rpassword::prompt_password("New wallet password: ")?;
 
-    let bytes = {
-        let mut bytes = [0; 184];
-        file.read_exact(&mut bytes)?;
-        hex::decode(bytes)?
-    };
+    if password != rpassword::prompt_password("Repeat the new password: ")? {
+        return Err(anyhow!("Passwords do not match"));
+    }
+
+    Ok(password)
+}
+
+/// Loading the wallet from wallet.dat, unversioned files are migrated to the current format
+pub fn load(wallet_path: &str, password: &str) -> Result<Wallet> {
+    let bytes = fs::read(wallet_path)?;
+
+    if !bytes.starts_with(WALLET_MAGIC) {
+        // Migrated wallets are encrypted with the default parameters
+        let wallet = legacy::load(&String::from_utf8(bytes)?, password)?;
+
+        // The old file is replaced only once the migrated one is written, no copy encrypted
+        // with the weak legacy parameters is kept
+        save(wallet_path, password, &wallet)?;
+        log::info!("Wallet is migrated to version {WALLET_VERSION}");
 
-    let mut salt = [0u8; 32];
-    salt.copy_from_slice(&bytes[0..32]);
-    let mut nonce = [0u8; 12];
-    nonce.copy_from_slice(&bytes[32..44]);
-    let mut ciphertext = [0u8; 48];
-    ciphertext.copy_from_slice(&bytes[44..92]);
+        return Ok(wallet);
+    }
 
-    let cipher_key = argon2_key_derivation(password.as_bytes(), &salt)?;
-    let cipher = ChaCha20Poly1305::new_from_slice(&cipher_key)?;
+    let version = bytes.get(WALLET_MAGIC.len()).copied().unwrap_or_default();
+    if version != WALLET_VERSION {
+        return Err(anyhow!("Wallet version {version} is not supported"));
+    }
 
+    let offset = WALLET_MAGIC.len() + 1;
+    let header: Header = bincode::deserialize(&bytes[offset..])?;
+    let offset = offset + bincode::serialized_size(&header)? as usize;
+    let (aad, ciphertext) = bytes.split_at(offset);
+
+    let cipher = ChaCha20Poly1305::new_from_slice(&header.kdf.derive_key(password)?)?;
     let plaintext = cipher
-        .decrypt(&nonce.try_into()?, ciphertext.as_slice())
+        .decrypt(
+            &header.nonce.into(),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
         .map_err(|error| anyhow!("ChaCha20Poly1305 decrypt failed: {error:?}"))?;
 
-    let secret_key = ed25519_dalek::SecretKey::from_bytes(plaintext.as_slice())
-        .map_err(|error| anyhow!("Secret key serialization failed: {error:?}"))?;
+    let mut wallet: Wallet = bincode::deserialize(&plaintext)?;
+    wallet.kdf = header.kdf;
 
-    let public_key = ed25519_dalek::PublicKey::from(&secret_key);
-
-    Ok((secret_key.to_bytes(), public_key.to_bytes()))
+    Ok(wallet)
 }
 
-/// Saving a secret key in wallet.dat
-pub fn save(wallet_path: &str, secret_key: SecretKey) -> Result<()> {
-    let password = rpassword::prompt_password("Wallet password: ")?;
-
-    let salt: [u8; 32] = rand::random();
-    let nonce: [u8; 12] = rand::random();
+/// Saving the wallet in wallet.dat
+pub fn save(wallet_path: &str, password: &str, wallet: &Wallet) -> Result<()> {
+    let header = Header {
+        kdf: KdfParams::new(wallet.kdf.m_cost, wallet.kdf.t_cost, wallet.kdf.p_cost),
+        nonce: rand::random(),
+    };
 
-    let cipher_key = argon2_key_derivation(password.as_bytes(), &salt)?;
-    let cipher = ChaCha20Poly1305::new_from_slice(&cipher_key)?;
+    let mut bytes = WALLET_MAGIC.to_vec();
+    bytes.push(WALLET_VERSION);
+    bytes.extend(bincode::serialize(&header)?);
 
+    let cipher = ChaCha20Poly1305::new_from_slice(&header.kdf.derive_key(password)?)?;
+    let plaintext = bincode::serialize(wallet)?;
     let ciphertext = cipher
-        .encrypt(&nonce.try_into()?, secret_key.as_slice())
+        .encrypt(
+            &header.nonce.into(),
+            Payload {
+                msg: &plaintext,
+                aad: &bytes,
+            },
+        )
         .map_err(|error| anyhow!("ChaCha20Poly1305 encrypt failed: {error:?}"))?;
+    bytes.extend(ciphertext);
 
-    let mut bytes = [0; 92];
-    bytes[0..32].copy_from_slice(&salt);
-    bytes[32..44].copy_from_slice(&nonce);
-    bytes[44..92].copy_from_slice(ciphertext.as_slice());
+    // The wallet is replaced by renaming a synced temporary file, a crash keeps the old wallet
+    let tmp_path = format!("{wallet_path}.tmp");
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
 
-    let mut file = File::create(wallet_path)?;
-    file.write_all(hex::encode(bytes).as_bytes())?;
+    let mut file = options
+        .open(&tmp_path)
+        .map_err(|error| anyhow!("Failed to create {tmp_path}: {error}"))?;
+    file.write_all(&bytes)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp_path, wallet_path)
+        .map_err(|error| anyhow!("Failed to replace {wallet_path}: {error}"))?;
 
     Ok(())
 }
 
-fn argon2_key_derivation(password: &[u8], salt: &[u8; 32]) -> Result<Hash> {
-    let mut builder = ParamsBuilder::new();
-
-    builder.m_cost(1024);
-    builder.t_cost(1);
-    builder.p_cost(1);
-
-    let params = builder
-        .build()
-        .map_err(|error| anyhow!("Argon2 params build failed: {error:?}"))?;
-    let ctx = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+fn keypair(secret_key: SecretKey) -> Result<(SecretKey, PublicKey)> {
+    let secret_key = ed25519_dalek::SecretKey::from_bytes(&secret_key)
+        .map_err(|error| anyhow!("Secret key serialization failed: {error:?}"))?;
+    let public_key = ed25519_dalek::PublicKey::from(&secret_key);
 
-    let mut bytes = [0; 32];
-    ctx.hash_password_into(password, salt, &mut bytes)
-        .map_err(|error| anyhow!("Argon2 hashing failed: {error:?}"))?;
-    Ok(bytes)
+    Ok((secret_key.to_bytes(), public_key.to_bytes()))
 }
 
 /// Generation of a random ed25519 keypair
@@ -116,3 +367,92 @@ fn generate_keypair() -> ed25519_dalek::Keypair {
     let mut csprng = OsRng {};
     ed25519_dalek::Keypair::generate(&mut csprng)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("gem-{name}-{}.dat", std::process::id()));
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = temp_path("wallet");
+
+        let mut wallet = Wallet::from_mnemonic(&hd::generate_mnemonic().unwrap(), "", 5);
+        assert_eq!(wallet.add_account("savings", 10).unwrap(), 1);
+        let (secret_key, public_key) = generate();
+        assert_eq!(wallet.import_key(secret_key, "", 12), 2);
+        wallet.set_kdf(KdfParams::new(1024, 2, 1)).unwrap();
+
+        save(&path, "password", &wallet).unwrap();
+        assert!(load(&path, "wrong").is_err());
+        let loaded = load(&path, "password").unwrap();
+
+        // Changing the password keeps the parameters and renews the salt
+        save(&path, "new password", &loaded).unwrap();
+        assert!(load(&path, "password").is_err());
+        let reloaded = load(&path, "new password").unwrap();
+
+        // The wallet is only readable by the owner and the temporary file is renamed
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert!(!std::path::Path::new(&format!("{path}.tmp")).exists());
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.accounts(), wallet.accounts());
+        assert_eq!(reloaded.accounts(), wallet.accounts());
+        assert_eq!(reloaded.kdf().t_cost, 2);
+        assert_ne!(reloaded.kdf().salt, loaded.kdf().salt);
+        assert_eq!(loaded.accounts()[1].label, "savings");
+        assert_eq!(loaded.accounts()[2].label, "Account 2");
+        assert_eq!(loaded.keypair(2).unwrap(), (secret_key, public_key));
+        assert_ne!(loaded.keypair(0).unwrap(), loaded.keypair(1).unwrap());
+        assert!(loaded.keypair(3).is_err());
+        assert_eq!(loaded.find_account(public_key).unwrap(), Some(2));
+        assert_eq!(
+            loaded
+                .find_account(crate::constants::EMPTY_PUBLIC_KEY)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn migrate_unversioned_wallet() {
+        let path = temp_path("legacy-wallet");
+        let (secret_key, public_key) = generate();
+
+        // The first wallet format: hex of the salt, nonce and encrypted secret key
+        let kdf = KdfParams::new(1024, 1, 1);
+        let nonce: [u8; 12] = rand::random();
+        let cipher =
+            ChaCha20Poly1305::new_from_slice(&kdf.derive_key("password").unwrap()).unwrap();
+        let ciphertext = cipher
+            .encrypt(&nonce.into(), secret_key.as_slice())
+            .unwrap();
+        let bytes = [kdf.salt.as_slice(), &nonce, &ciphertext].concat();
+        fs::write(&path, hex::encode(bytes)).unwrap();
+
+        let wallet = load(&path, "password").unwrap();
+        assert_eq!(wallet.keypair(0).unwrap(), (secret_key, public_key));
+
+        // The file is rewritten in the current format with the default parameters
+        assert!(fs::read(&path).unwrap().starts_with(WALLET_MAGIC));
+        let migrated = load(&path, "password").unwrap();
+        assert_eq!(migrated.accounts(), wallet.accounts());
+        assert_eq!(migrated.kdf().m_cost, KdfParams::default().m_cost);
+
+        // The legacy file is not kept next to the wallet
+        assert!(!std::path::Path::new(&format!("{path}.bak")).exists());
+        assert!(!std::path::Path::new(&format!("{path}.tmp")).exists());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+use super::{load, prompt_password};
+use crate::primitive::*;
+use anyhow::{anyhow, Result};
+use std::{
+    env, fs,
+    time::{Duration, Instant},
+};
+
+/// Source of the wallet password
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasswordSource {
+    /// Asking in the terminal
+    Prompt,
+    /// Reading the inherited file descriptor
+    Fd(u32),
+    /// Reading the file
+    File(String),
+    /// Reading the environment variable
+    Env(String),
+}
+
+impl PasswordSource {
+    pub fn read(&self) -> Result<String> {
+        let password = match self {
+            Self::Prompt => return prompt_password(),
+            Self::Fd(fd) => fs::read_to_string(format!("/dev/fd/{fd}"))
+                .map_err(|error| anyhow!("Failed to read the password from fd {fd}: {error}"))?,
+            Self::File(path) => fs::read_to_string(path)
+                .map_err(|error| anyhow!("Failed to read the password file {path}: {error}"))?,
+            Self::Env(name) => env::var(name)
+                .map_err(|error| anyhow!("Failed to read the password from ${name}: {error}"))?,
+        };
+
+        // Only the line break is stripped, the password may end with spaces
+        Ok(password.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// Mining keypair of the wallet account, kept decrypted until it is locked or the timeout expires
+pub struct MiningKey {
+    wallet_path: String,
+    account: usize,
+    keypair: Option<(SecretKey, PublicKey)>,
+    deadline: Option<Instant>,
+}
+
+impl MiningKey {
+    pub fn new(wallet_path: &str, account: usize) -> Self {
+        Self {
+            wallet_path: wallet_path.to_string(),
+            account,
+            keypair: None,
+            deadline: None,
+        }
+    }
+
+    /// Decrypting the wallet and keeping the account keypair, forever if there is no timeout
+    pub fn unlock(&mut self, password: &str, timeout: Option<Duration>) -> Result<PublicKey> {
+        let wallet = load(&self.wallet_path, password)?;
+        let keypair = wallet.keypair(self.account)?;
+
+        self.keypair = Some(keypair);
+        self.deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        Ok(keypair.1)
+    }
+
+    pub fn lock(&mut self) {
+        self.keypair = None;
+        self.deadline = None;
+    }
+
+    /// Keypair of the unlocked wallet, the wallet is locked if the timeout has expired
+    pub fn keypair(&mut self) -> Option<(SecretKey, PublicKey)> {
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                log::info!("Wallet is locked after the unlock timeout");
+                self.lock();
+            }
+        }
+
+        self.keypair
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::{hd, save, KdfParams, Wallet};
+
+    #[test]
+    fn unlock_with_timeout() {
+        let dir = env::temp_dir();
+        let wallet_path = dir.join(format!("gem-unlock-{}.dat", std::process::id()));
+        let wallet_path = wallet_path.to_str().unwrap();
+        let password_path = format!("{wallet_path}.password");
+
+        let mut wallet = Wallet::from_mnemonic(&hd::generate_mnemonic().unwrap(), "", 0);
+        wallet.set_kdf(KdfParams::new(1024, 1, 1)).unwrap();
+        save(wallet_path, "secret ", &wallet).unwrap();
+
+        fs::write(&password_path, "secret \n").unwrap();
+        let password = PasswordSource::File(password_path.clone()).read().unwrap();
+        fs::remove_file(&password_path).unwrap();
+        assert_eq!(password, "secret ");
+
+        let mut mining_key = MiningKey::new(wallet_path, 0);
+        assert!(mining_key.unlock("wrong", None).is_err());
+        assert!(mining_key.keypair().is_none());
+
+        let public_key = mining_key.unlock(&password, Some(Duration::ZERO)).unwrap();
+        fs::remove_file(wallet_path).unwrap();
+        assert_eq!(public_key, wallet.keypair(0).unwrap().1);
+        assert!(mining_key.keypair().is_none());
+    }
+}